@@ -0,0 +1,345 @@
+use crate::status_flags::StatusFlags;
+
+pub fn set_zero_negative(flags: &mut StatusFlags, value: u8) {
+    flags.zero = value == 0;
+    flags.negative = value & 0x80 != 0;
+}
+
+// V is set when both operands have the same sign and the sign of the
+// result differs from it.
+pub fn add(flags: &mut StatusFlags, a: u8, b: u8) -> u8 {
+    let sum = a as u16 + b as u16 + flags.carry as u16;
+    let result = sum as u8;
+    flags.carry = sum > 0xff;
+    flags.overflow = (a ^ result) & (b ^ result) & 0x80 != 0;
+    set_zero_negative(flags, result);
+    result
+}
+
+// Binary subtraction is addition of the one's complement, with the carry
+// acting as an inverted borrow.
+pub fn subtract(flags: &mut StatusFlags, a: u8, b: u8) -> u8 {
+    add(flags, a, !b)
+}
+
+// NMOS decimal mode: Z comes from the binary sum, N and V from the sum
+// after only the low nibble has been adjusted.
+pub fn add_decimal(flags: &mut StatusFlags, a: u8, b: u8) -> u8 {
+    let a = a as u16;
+    let b = b as u16;
+    let c = flags.carry as u16;
+
+    let mut lo = (a & 0x0f) + (b & 0x0f) + c;
+    if lo > 0x09 {
+        lo = ((lo + 0x06) & 0x0f) + 0x10;
+    }
+    let mut tmp = (a & 0xf0) + (b & 0xf0) + lo;
+
+    flags.zero = (a + b + c) & 0xff == 0;
+    flags.negative = tmp & 0x80 != 0;
+    flags.overflow = (a ^ tmp) & 0x80 != 0 && (a ^ b) & 0x80 == 0;
+
+    if tmp >= 0xa0 {
+        tmp += 0x60;
+    }
+    flags.carry = tmp >= 0x100;
+    tmp as u8
+}
+
+// NMOS decimal mode: all flags come from the binary difference.
+pub fn subtract_decimal(flags: &mut StatusFlags, a: u8, b: u8) -> u8 {
+    let a = a as i16;
+    let b = b as i16;
+    let borrow = !flags.carry as i16;
+
+    let binary = a - b - borrow;
+    flags.carry = binary >= 0;
+    flags.zero = binary & 0xff == 0;
+    flags.negative = binary & 0x80 != 0;
+    flags.overflow = (a ^ binary) & 0x80 != 0 && (a ^ b) & 0x80 != 0;
+
+    let mut lo = (a & 0x0f) - (b & 0x0f) - borrow;
+    if lo < 0 {
+        lo = ((lo - 0x06) & 0x0f) - 0x10;
+    }
+    let mut tmp = (a & 0xf0) - (b & 0xf0) + lo;
+    if tmp < 0 {
+        tmp -= 0x60;
+    }
+    tmp as u8
+}
+
+// 65C02 decimal mode: C and V as on NMOS parts, N and Z from the adjusted
+// result.
+pub fn add_decimal_cmos(flags: &mut StatusFlags, a: u8, b: u8) -> u8 {
+    let result = add_decimal(flags, a, b);
+    set_zero_negative(flags, result);
+    result
+}
+
+pub fn subtract_decimal_cmos(flags: &mut StatusFlags, a: u8, b: u8) -> u8 {
+    let borrow = !flags.carry as i16;
+    subtract_decimal(flags, a, b);
+
+    let a = a as i16;
+    let b = b as i16;
+    let lo = (a & 0x0f) - (b & 0x0f) - borrow;
+    let mut tmp = a - b - borrow;
+    if tmp < 0 {
+        tmp -= 0x60;
+    }
+    if lo < 0 {
+        tmp -= 0x06;
+    }
+    let result = tmp as u8;
+    set_zero_negative(flags, result);
+    result
+}
+
+// C is set when no borrow occurs, that is when register >= value.
+pub fn compare(flags: &mut StatusFlags, register: u8, value: u8) {
+    flags.carry = register >= value;
+    set_zero_negative(flags, register.wrapping_sub(value));
+}
+
+pub fn bit(flags: &mut StatusFlags, a: u8, value: u8) {
+    flags.zero = a & value == 0;
+    flags.negative = value & 0x80 != 0;
+    flags.overflow = value & 0x40 != 0;
+}
+
+pub fn shift_left(flags: &mut StatusFlags, value: u8) -> u8 {
+    let result = value << 1;
+    flags.carry = value & 0x80 != 0;
+    set_zero_negative(flags, result);
+    result
+}
+
+pub fn shift_right(flags: &mut StatusFlags, value: u8) -> u8 {
+    let result = value >> 1;
+    flags.carry = value & 0x01 != 0;
+    set_zero_negative(flags, result);
+    result
+}
+
+pub fn rotate_left(flags: &mut StatusFlags, value: u8) -> u8 {
+    let result = (value << 1) | flags.carry as u8;
+    flags.carry = value & 0x80 != 0;
+    set_zero_negative(flags, result);
+    result
+}
+
+pub fn rotate_right(flags: &mut StatusFlags, value: u8) -> u8 {
+    let result = (value >> 1) | (flags.carry as u8) << 7;
+    flags.carry = value & 0x01 != 0;
+    set_zero_negative(flags, result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Shift = fn(&mut StatusFlags, u8) -> u8;
+
+    fn with_carry(carry: bool) -> StatusFlags {
+        let mut flags = StatusFlags::new();
+        flags.carry = carry;
+        flags
+    }
+
+    fn check_zero_negative(flags: &StatusFlags, result: u8) {
+        assert_eq!(flags.zero, result == 0);
+        assert_eq!(flags.negative, result & 0x80 != 0);
+    }
+
+    #[test]
+    fn add_matches_reference() {
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                for carry in [false, true] {
+                    let sum = a as i32 + b as i32 + carry as i32;
+                    let signed = a as i8 as i32 + b as i8 as i32 + carry as i32;
+                    let mut flags = with_carry(carry);
+                    let result = add(&mut flags, a, b);
+                    assert_eq!(result, sum as u8);
+                    assert_eq!(flags.carry, sum > 0xff);
+                    assert_eq!(flags.overflow, !(-128..=127).contains(&signed));
+                    check_zero_negative(&flags, result);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn subtract_matches_reference() {
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                for carry in [false, true] {
+                    let borrow = !carry as i32;
+                    let difference = a as i32 - b as i32 - borrow;
+                    let signed = a as i8 as i32 - b as i8 as i32 - borrow;
+                    let mut flags = with_carry(carry);
+                    let result = subtract(&mut flags, a, b);
+                    assert_eq!(result, difference as u8);
+                    assert_eq!(flags.carry, difference >= 0);
+                    assert_eq!(flags.overflow, !(-128..=127).contains(&signed));
+                    check_zero_negative(&flags, result);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compare_matches_reference() {
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                for carry in [false, true] {
+                    let mut flags = with_carry(carry);
+                    flags.overflow = carry;
+                    compare(&mut flags, a, b);
+                    assert_eq!(flags.carry, a >= b);
+                    assert_eq!(flags.overflow, carry);
+                    check_zero_negative(&flags, a.wrapping_sub(b));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bit_matches_reference() {
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                for carry in [false, true] {
+                    let mut flags = with_carry(carry);
+                    bit(&mut flags, a, b);
+                    assert_eq!(flags.zero, a & b == 0);
+                    assert_eq!(flags.negative, b & 0x80 != 0);
+                    assert_eq!(flags.overflow, b & 0x40 != 0);
+                    assert_eq!(flags.carry, carry);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shifts_and_rotates_match_reference() {
+        for value in 0..=255u8 {
+            for carry in [false, true] {
+                let wide = value as u16;
+                let cases: [(Shift, u16, bool); 4] = [
+                    (shift_left, wide << 1, value & 0x80 != 0),
+                    (shift_right, wide >> 1, value & 0x01 != 0),
+                    (rotate_left, (wide << 1) | carry as u16, value & 0x80 != 0),
+                    (
+                        rotate_right,
+                        (wide >> 1) | (carry as u16) << 7,
+                        value & 0x01 != 0,
+                    ),
+                ];
+                for (fun, expected, carry_out) in cases {
+                    let mut flags = with_carry(carry);
+                    let result = fun(&mut flags, value);
+                    assert_eq!(result, expected as u8);
+                    assert_eq!(flags.carry, carry_out);
+                    check_zero_negative(&flags, result);
+                }
+            }
+        }
+    }
+
+    // Bruce Clark's "Decimal Mode" tutorial, appendix A: sequence 1 gives
+    // the ADC result and carry, sequence 2 the NMOS N and V flags.
+    fn clark_add(a: u8, b: u8, carry: bool) -> (u8, bool, bool, bool) {
+        let (a, b, c) = (a as i32, b as i32, carry as i32);
+        let mut low = (a & 0x0f) + (b & 0x0f) + c;
+        if low >= 0x0a {
+            low = ((low + 0x06) & 0x0f) + 0x10;
+        }
+        let mut result = (a & 0xf0) + (b & 0xf0) + low;
+        if result >= 0xa0 {
+            result += 0x60;
+        }
+        let signed = (a & 0xf0) as u8 as i8 as i32 + (b & 0xf0) as u8 as i8 as i32 + low;
+        let negative = signed & 0x80 != 0;
+        let overflow = !(-128..=127).contains(&signed);
+        (result as u8, result >= 0x100, negative, overflow)
+    }
+
+    // Sequence 3 gives the NMOS SBC result, sequence 4 the 65C02 one.
+    fn clark_subtract(a: u8, b: u8, carry: bool) -> (u8, u8) {
+        let (a, b, c) = (a as i32, b as i32, carry as i32);
+        let mut low = (a & 0x0f) - (b & 0x0f) + c - 1;
+        if low < 0 {
+            low = ((low - 0x06) & 0x0f) - 0x10;
+        }
+        let mut nmos = (a & 0xf0) - (b & 0xf0) + low;
+        if nmos < 0 {
+            nmos -= 0x60;
+        }
+
+        let low = (a & 0x0f) - (b & 0x0f) + c - 1;
+        let mut cmos = a - b + c - 1;
+        if cmos < 0 {
+            cmos -= 0x60;
+        }
+        if low < 0 {
+            cmos -= 0x06;
+        }
+        (nmos as u8, cmos as u8)
+    }
+
+    #[test]
+    fn add_decimal_matches_reference() {
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                for carry in [false, true] {
+                    let (expected, carry_out, negative, overflow) = clark_add(a, b, carry);
+                    let binary = a.wrapping_add(b).wrapping_add(carry as u8);
+
+                    let mut flags = with_carry(carry);
+                    let result = add_decimal(&mut flags, a, b);
+                    assert_eq!(result, expected);
+                    assert_eq!(flags.carry, carry_out);
+                    assert_eq!(flags.negative, negative);
+                    assert_eq!(flags.overflow, overflow);
+                    assert_eq!(flags.zero, binary == 0);
+
+                    let mut flags = with_carry(carry);
+                    let result = add_decimal_cmos(&mut flags, a, b);
+                    assert_eq!(result, expected);
+                    assert_eq!(flags.carry, carry_out);
+                    assert_eq!(flags.overflow, overflow);
+                    check_zero_negative(&flags, result);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn subtract_decimal_matches_reference() {
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                for carry in [false, true] {
+                    let (nmos, cmos) = clark_subtract(a, b, carry);
+                    let mut binary_flags = with_carry(carry);
+                    let binary = subtract(&mut binary_flags, a, b);
+
+                    let mut flags = with_carry(carry);
+                    let result = subtract_decimal(&mut flags, a, b);
+                    assert_eq!(result, nmos);
+                    assert_eq!(flags.carry, binary_flags.carry);
+                    assert_eq!(flags.overflow, binary_flags.overflow);
+                    check_zero_negative(&flags, binary);
+
+                    let mut flags = with_carry(carry);
+                    let result = subtract_decimal_cmos(&mut flags, a, b);
+                    assert_eq!(result, cmos);
+                    assert_eq!(flags.carry, binary_flags.carry);
+                    assert_eq!(flags.overflow, binary_flags.overflow);
+                    check_zero_negative(&flags, result);
+                }
+            }
+        }
+    }
+}
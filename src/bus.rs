@@ -0,0 +1,101 @@
+use std::ops::{Deref, DerefMut};
+
+pub trait Bus {
+    fn read(&mut self, address: u16) -> u8;
+    fn write(&mut self, address: u16, value: u8);
+    fn peek(&self, address: u16) -> u8;
+
+    fn write_slice(&mut self, data: &[u8], offset: u16) {
+        for (i, value) in data.iter().enumerate() {
+            self.write(offset.wrapping_add(i as u16), *value);
+        }
+    }
+}
+
+pub struct Memory {
+    pub data: [u8; 65536],
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self { data: [0; 65536] }
+    }
+}
+
+impl Bus for Memory {
+    fn read(&mut self, address: u16) -> u8 {
+        self.data[address as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.data[address as usize] = value;
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.data[address as usize]
+    }
+
+    // Wraps at $FFFF like the trait default, one copy per pass.
+    fn write_slice(&mut self, mut data: &[u8], offset: u16) {
+        let mut start = offset as usize;
+        while !data.is_empty() {
+            let len = data.len().min(self.data.len() - start);
+            self.data[start..start + len].copy_from_slice(&data[..len]);
+            data = &data[len..];
+            start = 0;
+        }
+    }
+}
+
+impl Deref for Memory {
+    type Target = [u8; 65536];
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl DerefMut for Memory {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Plain(Memory);
+
+    impl Bus for Plain {
+        fn read(&mut self, address: u16) -> u8 {
+            self.0.read(address)
+        }
+
+        fn write(&mut self, address: u16, value: u8) {
+            self.0.write(address, value)
+        }
+
+        fn peek(&self, address: u16) -> u8 {
+            self.0.peek(address)
+        }
+    }
+
+    #[test]
+    fn write_slice_wraps_at_end_of_memory() {
+        let data = [0x01, 0x02, 0x03, 0x04];
+        let mut memory = Memory::new();
+        let mut plain = Plain(Memory::new());
+        memory.write_slice(&data, 0xfffe);
+        plain.write_slice(&data, 0xfffe);
+        assert_eq!(memory[0xfffe..], [0x01, 0x02]);
+        assert_eq!(memory[..2], [0x03, 0x04]);
+        assert_eq!(memory.data, plain.0.data);
+    }
+}
@@ -0,0 +1,6 @@
+#[derive(Debug, Clone, Copy)]
+pub struct BusAccess {
+    pub address: u16,
+    pub value: u8,
+    pub write: bool,
+}
@@ -0,0 +1,10 @@
+use crate::cpu_error::CpuError;
+
+#[derive(Debug, Clone, Copy)]
+pub enum CallOutcome {
+    Returned,
+    BudgetExceeded,
+    Stopped,
+    Halted,
+    Fault(CpuError),
+}
@@ -0,0 +1,11 @@
+use crate::call_outcome::CallOutcome;
+use crate::registers::Registers;
+use crate::status_flags::StatusFlags;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CallResult {
+    pub outcome: CallOutcome,
+    pub registers: Registers,
+    pub status_flags: StatusFlags,
+    pub cycles: u64,
+}
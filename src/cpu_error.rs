@@ -0,0 +1,83 @@
+use std::fmt;
+
+use crate::instruction::Instruction;
+use crate::mode::Mode;
+
+#[derive(Debug, Clone, Copy)]
+pub enum CpuError {
+    UnknownOpcode {
+        program_counter: u16,
+        opcode: u8,
+    },
+    IllegalOpcode {
+        program_counter: u16,
+        opcode: u8,
+        mode: Mode,
+    },
+    UnsupportedInstruction {
+        program_counter: u16,
+        opcode: u8,
+        instruction: Instruction,
+    },
+    UnsupportedMode {
+        program_counter: u16,
+        opcode: u8,
+        mode: Mode,
+    },
+    UntickableOpcode {
+        program_counter: u16,
+        opcode: u8,
+    },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::UnknownOpcode {
+                program_counter,
+                opcode,
+            } => write!(
+                f,
+                "unknown opcode ${:02x} at ${:04x}",
+                opcode, program_counter
+            ),
+            CpuError::IllegalOpcode {
+                program_counter,
+                opcode,
+                mode,
+            } => write!(
+                f,
+                "illegal opcode ${:02x} ({:?}) at ${:04x}",
+                opcode, mode, program_counter
+            ),
+            CpuError::UnsupportedInstruction {
+                program_counter,
+                opcode,
+                instruction,
+            } => write!(
+                f,
+                "unsupported instruction {:?} (opcode ${:02x}) at ${:04x}",
+                instruction, opcode, program_counter
+            ),
+            CpuError::UnsupportedMode {
+                program_counter,
+                opcode,
+                mode,
+            } => write!(
+                f,
+                "unsupported addressing mode {:?} (opcode ${:02x}) at ${:04x}",
+                mode, opcode, program_counter
+            ),
+            CpuError::UntickableOpcode {
+                program_counter,
+                opcode,
+            } => write!(
+                f,
+                "opcode ${:02x} at ${:04x} runs a handler and cannot be ticked",
+                opcode, program_counter
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
@@ -0,0 +1,10 @@
+// What an instruction hook wants to happen next: Skip is only meaningful
+// before the instruction and makes step move the program counter past it,
+// by the operand length of its addressing mode, without executing it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HookAction {
+    #[default]
+    Continue,
+    Stop,
+    Skip,
+}
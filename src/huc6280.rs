@@ -0,0 +1,1075 @@
+use crate::alu;
+use crate::cpu_error::CpuError;
+use crate::instruction::Instruction;
+use crate::mode::Mode;
+use crate::opcodes::{self, OpCode};
+use crate::registers::Registers;
+use crate::status_flags::StatusFlags;
+use crate::wide_bus::{WideBus, WideMemory};
+
+const IRQ2_VECTOR: u16 = 0xfff6;
+const IRQ1_VECTOR: u16 = 0xfff8;
+const NMI_VECTOR: u16 = 0xfffc;
+const RESET_VECTOR: u16 = 0xfffe;
+
+const ZERO_PAGE: u16 = 0x2000;
+const STACK_PAGE: u16 = 0x2100;
+const VDC_PORTS: [u32; 3] = [0x1fe000, 0x1fe002, 0x1fe003];
+
+pub type HuC6280StepCallback<B> = Box<dyn FnMut(&HuC6280<B>)>;
+
+// The PC Engine CPU. Logical addresses are mapped to the 2MB physical
+// address space in 8KB pages by the mapping registers (MPR0-MPR7), and
+// the zero page and stack live at logical $2000 and $2100.
+pub struct HuC6280<B: WideBus = WideMemory> {
+    pub registers: Registers,
+    pub status_flags: StatusFlags,
+    pub memory_operation: bool,
+    pub mpr: [u8; 8],
+    pub high_speed: bool,
+    pub memory: B,
+    pub cycles: u64,
+    pub current_opcode: OpCode,
+
+    pub irq: bool,
+    pub nmi: bool,
+    pub nmi_pending: bool,
+
+    pub step_callback: Option<HuC6280StepCallback<B>>,
+
+    instruction_address: u16,
+    opcode: u8,
+}
+
+impl Default for HuC6280 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HuC6280 {
+    pub fn new() -> HuC6280 {
+        Self::with_bus(WideMemory::new())
+    }
+}
+
+impl<B: WideBus> HuC6280<B> {
+    pub fn with_bus(memory: B) -> HuC6280<B> {
+        // Powers up with SP at $00, see `CPU::with_bus_and_variant`.
+        let registers = Registers {
+            stack_pointer: 0x00,
+            ..Registers::new()
+        };
+        let status_flags = StatusFlags::new();
+        let memory_operation = false;
+        // I/O in the first page and RAM behind the zero page, as set up
+        // by the system card.
+        let mpr = [0xff, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let high_speed = false;
+        let cycles = 0;
+        let current_opcode = None;
+
+        let irq = false;
+        let nmi = false;
+        let nmi_pending = false;
+
+        let step_callback = None;
+
+        let instruction_address = 0;
+        let opcode = 0;
+
+        HuC6280 {
+            registers,
+            status_flags,
+            memory_operation,
+            mpr,
+            high_speed,
+            memory,
+            cycles,
+            current_opcode,
+            irq,
+            nmi,
+            nmi_pending,
+            step_callback,
+            instruction_address,
+            opcode,
+        }
+    }
+
+    pub fn set_step_callback(&mut self, fun: HuC6280StepCallback<B>) {
+        self.step_callback = Some(fun);
+    }
+
+    pub fn set_irq(&mut self, level: bool) {
+        self.irq = level;
+    }
+
+    pub fn set_nmi(&mut self, level: bool) {
+        if level && !self.nmi {
+            self.nmi_pending = true;
+        }
+        self.nmi = level;
+    }
+
+    // Same as `CPU::reset`: SP drops by three and only I, D, T, the clock
+    // speed and MPR7 are set up; a cold reset leaves SP at $FD.
+    pub fn reset(&mut self) {
+        self.nmi_pending = false;
+        self.registers.stack_pointer = self.registers.stack_pointer.wrapping_sub(3);
+        self.status_flags.interrupt = true;
+        self.status_flags.decimal = false;
+        self.memory_operation = false;
+        self.high_speed = false;
+        self.mpr[7] = 0x00;
+        self.registers.program_counter = self.read_word(RESET_VECTOR);
+    }
+
+    pub fn reset_to(&mut self, program_counter: u16, accumulator: u8) {
+        self.registers = Registers::new();
+        self.status_flags = StatusFlags::new();
+        self.memory_operation = false;
+
+        self.registers.accumulator = accumulator;
+        self.registers.program_counter = program_counter;
+    }
+
+    pub fn status_byte(&self) -> u8 {
+        let mut byte = self.status_flags.to_byte();
+        if self.memory_operation {
+            byte |= 0x20;
+        }
+        byte
+    }
+
+    pub fn set_status_byte(&mut self, byte: u8) {
+        self.status_flags = StatusFlags::from_byte(byte);
+        self.memory_operation = byte & 0x20 != 0;
+    }
+
+    pub fn physical_address(&self, address: u16) -> u32 {
+        (self.mpr[(address >> 13) as usize] as u32) << 13 | (address & 0x1fff) as u32
+    }
+
+    pub fn step(&mut self) -> Result<u64, CpuError> {
+        let interrupt = if self.nmi_pending {
+            self.nmi_pending = false;
+            Some(NMI_VECTOR)
+        } else if self.irq && !self.status_flags.interrupt {
+            Some(IRQ1_VECTOR)
+        } else {
+            None
+        };
+
+        if let Some(vector) = interrupt {
+            self.cycles = 8;
+            self.interrupt(vector, false);
+        } else {
+            let program_counter = self.registers.program_counter;
+            let memory_operation = self.memory_operation;
+            if let Err(error) = self.execute() {
+                self.registers.program_counter = program_counter;
+                self.memory_operation = memory_operation;
+                return Err(error);
+            }
+        }
+        Ok(self.cycles)
+    }
+
+    fn execute(&mut self) -> Result<(), CpuError> {
+        self.instruction_address = self.registers.program_counter;
+        let opcode = self.read_byte_and_increment_pc();
+        self.opcode = opcode;
+        self.current_opcode = opcodes::get_huc6280(opcode);
+        self.cycles = opcodes::cycles_huc6280(opcode) as u64;
+
+        if let Some(mut step_callback) = self.step_callback.take() {
+            step_callback(self);
+            self.step_callback.get_or_insert(step_callback);
+        }
+
+        // The T flag only lasts for the instruction following SET.
+        let memory_operation = self.memory_operation;
+        self.memory_operation = false;
+
+        if let Some((instruction, mode)) = self.current_opcode {
+            match instruction {
+                Instruction::AddWithCarry => {
+                    let value = self.get_operand(mode)?;
+                    let tmp = self.load_target(memory_operation);
+                    let tmp = self.add_with_carry(tmp, value);
+                    self.store_target(memory_operation, tmp);
+                }
+                Instruction::AndWithAccumulator => {
+                    let value = self.get_operand(mode)?;
+                    let tmp = self.load_target(memory_operation) & value;
+                    self.set_zero_negative(tmp);
+                    self.store_target(memory_operation, tmp);
+                }
+                Instruction::ArithmeticShiftLeft => {
+                    let (address, value) = self.get_operand_modify(mode)?;
+                    let tmp = alu::shift_left(&mut self.status_flags, value);
+                    self.set_operand_modify(address, tmp);
+                }
+                Instruction::BranchIfCarryClear => {
+                    self.branch(!self.status_flags.carry);
+                }
+                Instruction::BranchIfCarrySet => {
+                    self.branch(self.status_flags.carry);
+                }
+                Instruction::BranchIfEqual => {
+                    self.branch(self.status_flags.zero);
+                }
+                Instruction::BranchIfMinus => {
+                    self.branch(self.status_flags.negative);
+                }
+                Instruction::BranchIfNotEqual => {
+                    self.branch(!self.status_flags.zero);
+                }
+                Instruction::BranchIfPlus => {
+                    self.branch(!self.status_flags.negative);
+                }
+                Instruction::BranchIfOverflowClear => {
+                    self.branch(!self.status_flags.overflow);
+                }
+                Instruction::BranchIfOverflowSet => {
+                    self.branch(self.status_flags.overflow);
+                }
+                Instruction::BitSet => {
+                    let tmp = self.get_operand(mode)?;
+                    self.status_flags.zero = self.registers.accumulator & tmp == 0;
+                    if !matches!(mode, Mode::Immediate) {
+                        self.status_flags.negative = tmp & 0x80 != 0;
+                        self.status_flags.overflow = tmp & 0x40 != 0;
+                    }
+                }
+                Instruction::Break => {
+                    self.read_byte_and_increment_pc();
+                    self.interrupt(IRQ2_VECTOR, true);
+                }
+                Instruction::ClearCarry => {
+                    self.status_flags.carry = false;
+                }
+                Instruction::ClearDecimal => {
+                    self.status_flags.decimal = false;
+                }
+                Instruction::ClearInterrupt => {
+                    self.status_flags.interrupt = false;
+                }
+                Instruction::ClearOverflow => {
+                    self.status_flags.overflow = false;
+                }
+                Instruction::CompareWithAccumulator => {
+                    let value = self.get_operand(mode)?;
+                    self.compare(self.registers.accumulator, value);
+                }
+                Instruction::CompareWithX => {
+                    let value = self.get_operand(mode)?;
+                    self.compare(self.registers.x, value);
+                }
+                Instruction::CompareWithY => {
+                    let value = self.get_operand(mode)?;
+                    self.compare(self.registers.y, value);
+                }
+                Instruction::Decrement => {
+                    let (address, value) = self.get_operand_modify(mode)?;
+                    let tmp = value.wrapping_sub(1);
+                    self.set_zero_negative(tmp);
+                    self.set_operand_modify(address, tmp);
+                }
+                Instruction::DecrementX => {
+                    self.registers.x = self.registers.x.wrapping_sub(1);
+                    self.set_zero_negative(self.registers.x);
+                }
+                Instruction::DecrementY => {
+                    self.registers.y = self.registers.y.wrapping_sub(1);
+                    self.set_zero_negative(self.registers.y);
+                }
+                Instruction::ExclusiveOrWithAccumulator => {
+                    let value = self.get_operand(mode)?;
+                    let tmp = self.load_target(memory_operation) ^ value;
+                    self.set_zero_negative(tmp);
+                    self.store_target(memory_operation, tmp);
+                }
+                Instruction::Increment => {
+                    let (address, value) = self.get_operand_modify(mode)?;
+                    let tmp = value.wrapping_add(1);
+                    self.set_zero_negative(tmp);
+                    self.set_operand_modify(address, tmp);
+                }
+                Instruction::IncrementX => {
+                    self.registers.x = self.registers.x.wrapping_add(1);
+                    self.set_zero_negative(self.registers.x);
+                }
+                Instruction::IncrementY => {
+                    self.registers.y = self.registers.y.wrapping_add(1);
+                    self.set_zero_negative(self.registers.y);
+                }
+                Instruction::Jump => {
+                    let address = self.read_word_and_increment_pc();
+                    self.registers.program_counter = match mode {
+                        Mode::Absolute => address,
+                        Mode::Indirect => self.read_word(address),
+                        Mode::AbsoluteXIndirect => {
+                            self.read_word(address.wrapping_add(self.registers.x as u16))
+                        }
+                        _ => return Err(self.unsupported_mode(mode)),
+                    };
+                }
+                Instruction::JumpSubroutine => {
+                    let address = self.read_word_and_increment_pc();
+                    self.push_word(self.registers.program_counter.wrapping_sub(1));
+                    self.registers.program_counter = address;
+                }
+                Instruction::LoadAccumulator => {
+                    self.registers.accumulator = self.get_operand(mode)?;
+                    self.set_zero_negative(self.registers.accumulator);
+                }
+                Instruction::LoadX => {
+                    self.registers.x = self.get_operand(mode)?;
+                    self.set_zero_negative(self.registers.x);
+                }
+                Instruction::LoadY => {
+                    self.registers.y = self.get_operand(mode)?;
+                    self.set_zero_negative(self.registers.y);
+                }
+                Instruction::LogicalShiftRight => {
+                    let (address, value) = self.get_operand_modify(mode)?;
+                    let tmp = alu::shift_right(&mut self.status_flags, value);
+                    self.set_operand_modify(address, tmp);
+                }
+                Instruction::NoOperation => {}
+                Instruction::OrWithAccumulator => {
+                    let value = self.get_operand(mode)?;
+                    let tmp = self.load_target(memory_operation) | value;
+                    self.set_zero_negative(tmp);
+                    self.store_target(memory_operation, tmp);
+                }
+                Instruction::PushAccumulator => {
+                    self.push(self.registers.accumulator);
+                }
+                Instruction::PushProcessorStatus => {
+                    self.push(self.status_byte() | 0x10);
+                }
+                Instruction::PullAccumulator => {
+                    self.registers.accumulator = self.pop();
+                    self.set_zero_negative(self.registers.accumulator);
+                }
+                Instruction::PullProcessorStatus => {
+                    let tmp = self.pop();
+                    self.set_status_byte(tmp);
+                }
+                Instruction::RotateLeft => {
+                    let (address, value) = self.get_operand_modify(mode)?;
+                    let tmp = alu::rotate_left(&mut self.status_flags, value);
+                    self.set_operand_modify(address, tmp);
+                }
+                Instruction::RotateRight => {
+                    let (address, value) = self.get_operand_modify(mode)?;
+                    let tmp = alu::rotate_right(&mut self.status_flags, value);
+                    self.set_operand_modify(address, tmp);
+                }
+                Instruction::ReturnFromInterrupt => {
+                    let tmp = self.pop();
+                    self.set_status_byte(tmp);
+                    self.registers.program_counter = self.pop_word();
+                }
+                Instruction::ReturnFromSubroutine => {
+                    self.registers.program_counter = self.pop_word().wrapping_add(1);
+                }
+                Instruction::SubtractWithCarry => {
+                    let value = self.get_operand(mode)?;
+                    self.subtract_with_carry(value);
+                }
+                Instruction::SetCarry => {
+                    self.status_flags.carry = true;
+                }
+                Instruction::SetDecimal => {
+                    self.status_flags.decimal = true;
+                }
+                Instruction::SetInterruptDisable => {
+                    self.status_flags.interrupt = true;
+                }
+                Instruction::StoreAccumulator => {
+                    self.put_operand(mode, self.registers.accumulator)?;
+                }
+                Instruction::StoreX => {
+                    self.put_operand(mode, self.registers.x)?;
+                }
+                Instruction::StoreY => {
+                    self.put_operand(mode, self.registers.y)?;
+                }
+                Instruction::TransferAccumulatorToX => {
+                    self.registers.x = self.registers.accumulator;
+                    self.set_zero_negative(self.registers.x);
+                }
+                Instruction::TransferAccumulatorToY => {
+                    self.registers.y = self.registers.accumulator;
+                    self.set_zero_negative(self.registers.y);
+                }
+                Instruction::TransferStackPointerToX => {
+                    self.registers.x = self.registers.stack_pointer;
+                    self.set_zero_negative(self.registers.x);
+                }
+                Instruction::TransferXToAccumulator => {
+                    self.registers.accumulator = self.registers.x;
+                    self.set_zero_negative(self.registers.accumulator);
+                }
+                Instruction::TransferXToStackPointer => {
+                    self.registers.stack_pointer = self.registers.x;
+                }
+                Instruction::TransferYToAccumulator => {
+                    self.registers.accumulator = self.registers.y;
+                    self.set_zero_negative(self.registers.accumulator);
+                }
+                Instruction::BranchAlways => {
+                    self.branch(true);
+                }
+                Instruction::BranchIfBitReset(bit) => {
+                    let address = self.get_address(Mode::ZeroPage)?;
+                    let tmp = self.read_byte(address);
+                    self.branch(tmp & (1 << bit) == 0);
+                }
+                Instruction::BranchIfBitSet(bit) => {
+                    let address = self.get_address(Mode::ZeroPage)?;
+                    let tmp = self.read_byte(address);
+                    self.branch(tmp & (1 << bit) != 0);
+                }
+                Instruction::PullX => {
+                    self.registers.x = self.pop();
+                    self.set_zero_negative(self.registers.x);
+                }
+                Instruction::PullY => {
+                    self.registers.y = self.pop();
+                    self.set_zero_negative(self.registers.y);
+                }
+                Instruction::PushX => {
+                    self.push(self.registers.x);
+                }
+                Instruction::PushY => {
+                    self.push(self.registers.y);
+                }
+                Instruction::ResetMemoryBit(bit) => {
+                    let (address, value) = self.get_operand_modify(mode)?;
+                    self.set_operand_modify(address, value & !(1 << bit));
+                }
+                Instruction::SetMemoryBit(bit) => {
+                    let (address, value) = self.get_operand_modify(mode)?;
+                    self.set_operand_modify(address, value | (1 << bit));
+                }
+                Instruction::StoreZero => {
+                    self.put_operand(mode, 0)?;
+                }
+                Instruction::TestAndResetBits => {
+                    let (address, value) = self.get_operand_modify(mode)?;
+                    self.test_bits(value);
+                    self.set_operand_modify(address, value & !self.registers.accumulator);
+                }
+                Instruction::TestAndSetBits => {
+                    let (address, value) = self.get_operand_modify(mode)?;
+                    self.test_bits(value);
+                    self.set_operand_modify(address, value | self.registers.accumulator);
+                }
+                Instruction::BranchSubroutine => {
+                    self.push_word(self.registers.program_counter);
+                    self.branch(true);
+                }
+                Instruction::ChangeSpeedHigh => {
+                    self.high_speed = true;
+                }
+                Instruction::ChangeSpeedLow => {
+                    self.high_speed = false;
+                }
+                Instruction::ClearAccumulator => {
+                    self.registers.accumulator = 0;
+                }
+                Instruction::ClearX => {
+                    self.registers.x = 0;
+                }
+                Instruction::ClearY => {
+                    self.registers.y = 0;
+                }
+                Instruction::SetMemoryOperation => {
+                    self.memory_operation = true;
+                }
+                Instruction::StoreVdc(port) => {
+                    let value = self.read_byte_and_increment_pc();
+                    self.memory.write(VDC_PORTS[port as usize], value);
+                }
+                Instruction::SwapAccumulatorAndX => {
+                    let registers = &mut self.registers;
+                    std::mem::swap(&mut registers.accumulator, &mut registers.x);
+                }
+                Instruction::SwapAccumulatorAndY => {
+                    let registers = &mut self.registers;
+                    std::mem::swap(&mut registers.accumulator, &mut registers.y);
+                }
+                Instruction::SwapXAndY => {
+                    let registers = &mut self.registers;
+                    std::mem::swap(&mut registers.x, &mut registers.y);
+                }
+                Instruction::TestMemory => {
+                    let mask = self.read_byte_and_increment_pc();
+                    let tmp = self.get_operand(mode)?;
+                    self.status_flags.zero = mask & tmp == 0;
+                    self.status_flags.negative = tmp & 0x80 != 0;
+                    self.status_flags.overflow = tmp & 0x40 != 0;
+                }
+                Instruction::TransferAccumulatorToMpr => {
+                    let mask = self.read_byte_and_increment_pc();
+                    for (i, mpr) in self.mpr.iter_mut().enumerate() {
+                        if mask & (1 << i) != 0 {
+                            *mpr = self.registers.accumulator;
+                        }
+                    }
+                }
+                Instruction::TransferMprToAccumulator => {
+                    let mask = self.read_byte_and_increment_pc();
+                    for (i, mpr) in self.mpr.iter().enumerate() {
+                        if mask & (1 << i) != 0 {
+                            self.registers.accumulator = *mpr;
+                        }
+                    }
+                }
+                Instruction::TransferAlternateIncrement
+                | Instruction::TransferDecrementDecrement
+                | Instruction::TransferIncrementAlternate
+                | Instruction::TransferIncrementIncrement
+                | Instruction::TransferIncrementNone => {
+                    self.block_transfer(instruction);
+                }
+                _ => {
+                    return Err(CpuError::UnsupportedInstruction {
+                        program_counter: self.instruction_address,
+                        opcode,
+                        instruction,
+                    });
+                }
+            };
+            Ok(())
+        } else {
+            Err(self.unknown_opcode())
+        }
+    }
+
+    fn unknown_opcode(&self) -> CpuError {
+        CpuError::UnknownOpcode {
+            program_counter: self.instruction_address,
+            opcode: self.opcode,
+        }
+    }
+
+    fn unsupported_mode(&self, mode: Mode) -> CpuError {
+        CpuError::UnsupportedMode {
+            program_counter: self.instruction_address,
+            opcode: self.opcode,
+            mode,
+        }
+    }
+
+    fn interrupt(&mut self, vector: u16, brk: bool) {
+        self.push_word(self.registers.program_counter);
+        let status = self.status_byte();
+        self.push(if brk { status | 0x10 } else { status & !0x10 });
+        self.status_flags.interrupt = true;
+        self.status_flags.decimal = false;
+        self.memory_operation = false;
+        self.registers.program_counter = self.read_word(vector);
+    }
+
+    // The source, destination and length operands follow the opcode; Y, A
+    // and X are saved on the stack for the duration of the transfer.
+    fn block_transfer(&mut self, instruction: Instruction) {
+        let mut source = self.read_word_and_increment_pc();
+        let mut destination = self.read_word_and_increment_pc();
+        let length = self.read_word_and_increment_pc();
+        let length = if length == 0 { 0x10000 } else { length as u32 };
+
+        self.push(self.registers.y);
+        self.push(self.registers.accumulator);
+        self.push(self.registers.x);
+
+        for i in 0..length {
+            let alternate = if i & 1 == 0 { 1 } else { 0xffff };
+            let value = self.read_byte(source);
+            self.write_byte(destination, value);
+            match instruction {
+                Instruction::TransferIncrementIncrement => {
+                    source = source.wrapping_add(1);
+                    destination = destination.wrapping_add(1);
+                }
+                Instruction::TransferDecrementDecrement => {
+                    source = source.wrapping_sub(1);
+                    destination = destination.wrapping_sub(1);
+                }
+                Instruction::TransferIncrementNone => {
+                    source = source.wrapping_add(1);
+                }
+                Instruction::TransferIncrementAlternate => {
+                    source = source.wrapping_add(1);
+                    destination = destination.wrapping_add(alternate);
+                }
+                _ => {
+                    source = source.wrapping_add(alternate);
+                    destination = destination.wrapping_add(1);
+                }
+            }
+        }
+
+        self.registers.x = self.pop();
+        self.registers.accumulator = self.pop();
+        self.registers.y = self.pop();
+        self.cycles += 6 * length as u64;
+    }
+
+    fn set_zero_negative(&mut self, value: u8) {
+        alu::set_zero_negative(&mut self.status_flags, value);
+    }
+
+    fn test_bits(&mut self, value: u8) {
+        alu::bit(&mut self.status_flags, self.registers.accumulator, value);
+    }
+
+    fn compare(&mut self, register: u8, value: u8) {
+        alu::compare(&mut self.status_flags, register, value);
+    }
+
+    fn branch(&mut self, condition: bool) {
+        let offset = self.read_byte_and_increment_pc() as i8 as u16;
+        if condition {
+            if !matches!(
+                self.current_opcode,
+                Some((Instruction::BranchAlways | Instruction::BranchSubroutine, _))
+            ) {
+                self.cycles += 2;
+            }
+            self.registers.program_counter = self.registers.program_counter.wrapping_add(offset);
+        }
+    }
+
+    // With the T flag set ADC, AND, EOR and ORA operate on the zero page
+    // byte addressed by X instead of the accumulator.
+    fn load_target(&mut self, memory_operation: bool) -> u8 {
+        if memory_operation {
+            self.cycles += 3;
+            self.read_byte(ZERO_PAGE | self.registers.x as u16)
+        } else {
+            self.registers.accumulator
+        }
+    }
+
+    fn store_target(&mut self, memory_operation: bool, value: u8) {
+        if memory_operation {
+            self.write_byte(ZERO_PAGE | self.registers.x as u16, value);
+        } else {
+            self.registers.accumulator = value;
+        }
+    }
+
+    // Decimal mode behaves as on the 65C02: one extra cycle, with N and Z
+    // taken from the adjusted result.
+    fn add_with_carry(&mut self, a: u8, value: u8) -> u8 {
+        if self.status_flags.decimal {
+            self.cycles += 1;
+            alu::add_decimal_cmos(&mut self.status_flags, a, value)
+        } else {
+            alu::add(&mut self.status_flags, a, value)
+        }
+    }
+
+    fn subtract_with_carry(&mut self, value: u8) {
+        let a = self.registers.accumulator;
+        self.registers.accumulator = if self.status_flags.decimal {
+            self.cycles += 1;
+            alu::subtract_decimal_cmos(&mut self.status_flags, a, value)
+        } else {
+            alu::subtract(&mut self.status_flags, a, value)
+        };
+    }
+
+    pub fn read_byte(&mut self, address: u16) -> u8 {
+        self.memory.read(self.physical_address(address))
+    }
+
+    pub fn read_word(&mut self, address: u16) -> u16 {
+        self.read_byte(address) as u16 | (self.read_byte(address.wrapping_add(1)) as u16) << 8
+    }
+
+    pub fn peek_byte(&self, address: u16) -> u8 {
+        self.memory.peek(self.physical_address(address))
+    }
+
+    fn write_byte(&mut self, address: u16, value: u8) {
+        self.memory.write(self.physical_address(address), value);
+    }
+
+    pub fn write_slice(&mut self, data: &[u8], offset: u32) {
+        self.memory.write_slice(data, offset);
+    }
+
+    fn read_byte_and_increment_pc(&mut self) -> u8 {
+        let value = self.read_byte(self.registers.program_counter);
+        self.registers.program_counter = self.registers.program_counter.wrapping_add(1);
+        value
+    }
+
+    fn read_word_and_increment_pc(&mut self) -> u16 {
+        let low = self.read_byte_and_increment_pc() as u16;
+        let high = self.read_byte_and_increment_pc() as u16;
+        high << 8 | low
+    }
+
+    pub fn push(&mut self, value: u8) {
+        self.write_byte(STACK_PAGE | self.registers.stack_pointer as u16, value);
+        self.registers.stack_pointer = self.registers.stack_pointer.wrapping_sub(1);
+    }
+
+    pub fn pop(&mut self) -> u8 {
+        self.registers.stack_pointer = self.registers.stack_pointer.wrapping_add(1);
+        self.read_byte(STACK_PAGE | self.registers.stack_pointer as u16)
+    }
+
+    fn push_word(&mut self, value: u16) {
+        self.push((value >> 8) as u8);
+        self.push(value as u8);
+    }
+
+    fn pop_word(&mut self) -> u16 {
+        let low = self.pop() as u16;
+        let high = self.pop() as u16;
+        high << 8 | low
+    }
+
+    fn read_zero_page_word(&mut self, address: u8) -> u16 {
+        let low = self.read_byte(ZERO_PAGE | address as u16) as u16;
+        let high = self.read_byte(ZERO_PAGE | address.wrapping_add(1) as u16) as u16;
+        high << 8 | low
+    }
+
+    fn get_address(&mut self, mode: Mode) -> Result<u16, CpuError> {
+        Ok(match mode {
+            Mode::Absolute | Mode::ImmediateAbsolute => self.read_word_and_increment_pc(),
+            Mode::AbsoluteX | Mode::ImmediateAbsoluteX => self
+                .read_word_and_increment_pc()
+                .wrapping_add(self.registers.x as u16),
+            Mode::AbsoluteY => self
+                .read_word_and_increment_pc()
+                .wrapping_add(self.registers.y as u16),
+            Mode::ZeroPage | Mode::ImmediateZeroPage => {
+                ZERO_PAGE | self.read_byte_and_increment_pc() as u16
+            }
+            Mode::ZeroPageX | Mode::ImmediateZeroPageX => {
+                let address = self.read_byte_and_increment_pc();
+                ZERO_PAGE | address.wrapping_add(self.registers.x) as u16
+            }
+            Mode::ZeroPageY => {
+                let address = self.read_byte_and_increment_pc();
+                ZERO_PAGE | address.wrapping_add(self.registers.y) as u16
+            }
+            Mode::ZeroPageIndirect => {
+                let address = self.read_byte_and_increment_pc();
+                self.read_zero_page_word(address)
+            }
+            Mode::XIndirect => {
+                let address = self.read_byte_and_increment_pc();
+                self.read_zero_page_word(address.wrapping_add(self.registers.x))
+            }
+            Mode::IndirectY => {
+                let address = self.read_byte_and_increment_pc();
+                self.read_zero_page_word(address)
+                    .wrapping_add(self.registers.y as u16)
+            }
+            _ => return Err(self.unsupported_mode(mode)),
+        })
+    }
+
+    fn get_operand(&mut self, mode: Mode) -> Result<u8, CpuError> {
+        Ok(match mode {
+            Mode::Immediate => self.read_byte_and_increment_pc(),
+            _ => {
+                let address = self.get_address(mode)?;
+                self.read_byte(address)
+            }
+        })
+    }
+
+    fn put_operand(&mut self, mode: Mode, value: u8) -> Result<(), CpuError> {
+        let address = self.get_address(mode)?;
+        self.write_byte(address, value);
+        Ok(())
+    }
+
+    fn get_operand_modify(&mut self, mode: Mode) -> Result<(Option<u16>, u8), CpuError> {
+        Ok(match mode {
+            Mode::Accumulator => (None, self.registers.accumulator),
+            _ => {
+                let address = self.get_address(mode)?;
+                (Some(address), self.read_byte(address))
+            }
+        })
+    }
+
+    fn set_operand_modify(&mut self, address: Option<u16>, value: u8) {
+        match address {
+            Some(address) => self.write_byte(address, value),
+            None => self.registers.accumulator = value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    // The program sits at logical $E000, which MPR7 maps to physical $0000.
+    // MPR2 and MPR3 give logical $4000-$7FFF RAM of their own.
+    fn cpu_at(program: &[u8]) -> HuC6280 {
+        let mut cpu = HuC6280::new();
+        cpu.write_slice(program, 0x0000);
+        cpu.mpr[2] = 0x01;
+        cpu.mpr[3] = 0x02;
+        cpu.reset_to(0xe000, 0);
+        cpu
+    }
+
+    fn poke<B: WideBus>(cpu: &mut HuC6280<B>, address: u16, value: u8) {
+        let address = cpu.physical_address(address);
+        cpu.memory.write(address, value);
+    }
+
+    #[test]
+    fn cold_reset_sets_up_stack_flags_and_pc() {
+        let mut cpu = HuC6280::new();
+        // $FFFE through MPR7 = 0, physical $1FFE
+        cpu.write_slice(&[0x34, 0xe2], 0x1ffe);
+        cpu.status_flags.decimal = true;
+        cpu.mpr[7] = 0x05;
+        cpu.reset();
+        assert_eq!(cpu.registers.stack_pointer, 0xfd);
+        assert_eq!(cpu.registers.program_counter, 0xe234);
+        assert_eq!(cpu.mpr[7], 0x00);
+        assert!(cpu.status_flags.interrupt);
+        assert!(!cpu.status_flags.decimal);
+    }
+
+    #[test]
+    fn step_callback_runs_once_per_instruction() {
+        let mut cpu = HuC6280::new();
+        // NOP; NOP at logical $E000, which MPR7 maps to physical $0000
+        cpu.write_slice(&[0xea, 0xea], 0x0000);
+        cpu.reset_to(0xe000, 0);
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        cpu.set_step_callback(Box::new(move |_| counter.set(counter.get() + 1)));
+
+        assert_eq!(cpu.step().unwrap(), 2);
+        assert_eq!(cpu.step().unwrap(), 2);
+        assert_eq!(calls.get(), 2);
+        assert_eq!(cpu.registers.program_counter, 0xe002);
+    }
+
+    #[test]
+    fn mapping_registers_select_physical_pages() {
+        // TAM #$04; LDA $4005; CLA; TMA #$04; TAM #$30
+        let mut cpu = cpu_at(&[0x53, 0x04, 0xad, 0x05, 0x40, 0x62, 0x43, 0x04, 0x53, 0x30]);
+        cpu.registers.accumulator = 0x12;
+        cpu.memory[0x024005] = 0x77;
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.mpr[2], 0x12);
+        assert_eq!(cpu.physical_address(0x4005), 0x024005);
+        assert_eq!(cpu.physical_address(0x2000), 0x1f0000);
+        assert_eq!(cpu.physical_address(0xffff), 0x001fff);
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0x77);
+
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0x12);
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.mpr, [0xff, 0xf8, 0x12, 0x02, 0x12, 0x12, 0x00, 0x00]);
+    }
+
+    fn block_transfer(opcode: u8, source: u16, destination: u16, length: u16) -> HuC6280 {
+        let mut cpu = cpu_at(&[
+            opcode,
+            source as u8,
+            (source >> 8) as u8,
+            destination as u8,
+            (destination >> 8) as u8,
+            length as u8,
+            (length >> 8) as u8,
+        ]);
+        for (offset, value) in [0x11, 0x22, 0x33, 0x44].into_iter().enumerate() {
+            poke(&mut cpu, 0x4000 + offset as u16, value);
+        }
+        cpu.registers.accumulator = 0xaa;
+        cpu.registers.x = 0xbb;
+        cpu.registers.y = 0xcc;
+        let stack_pointer = cpu.registers.stack_pointer;
+
+        assert_eq!(cpu.step().unwrap(), 17 + 6 * length as u64);
+        assert_eq!(cpu.registers.program_counter, 0xe007);
+        assert_eq!(cpu.registers.stack_pointer, stack_pointer);
+        assert_eq!(
+            (cpu.registers.accumulator, cpu.registers.x, cpu.registers.y),
+            (0xaa, 0xbb, 0xcc)
+        );
+        cpu
+    }
+
+    fn destination(cpu: &HuC6280) -> [u8; 4] {
+        [0, 1, 2, 3].map(|offset| cpu.peek_byte(0x6000 + offset))
+    }
+
+    #[test]
+    fn block_transfers_step_source_and_destination() {
+        // TII
+        let cpu = block_transfer(0x73, 0x4000, 0x6000, 4);
+        assert_eq!(destination(&cpu), [0x11, 0x22, 0x33, 0x44]);
+
+        // TDD
+        let cpu = block_transfer(0xc3, 0x4003, 0x6003, 4);
+        assert_eq!(destination(&cpu), [0x11, 0x22, 0x33, 0x44]);
+
+        // TIN
+        let cpu = block_transfer(0xd3, 0x4000, 0x6000, 4);
+        assert_eq!(destination(&cpu), [0x44, 0x00, 0x00, 0x00]);
+
+        // TIA
+        let cpu = block_transfer(0xe3, 0x4000, 0x6000, 4);
+        assert_eq!(destination(&cpu), [0x33, 0x44, 0x00, 0x00]);
+
+        // TAI
+        let cpu = block_transfer(0xf3, 0x4000, 0x6000, 4);
+        assert_eq!(destination(&cpu), [0x11, 0x22, 0x11, 0x22]);
+    }
+
+    struct CountingBus {
+        memory: WideMemory,
+        writes: usize,
+    }
+
+    impl WideBus for CountingBus {
+        fn read(&mut self, address: u32) -> u8 {
+            self.memory.read(address)
+        }
+
+        fn write(&mut self, address: u32, value: u8) {
+            self.writes += 1;
+            self.memory.write(address, value);
+        }
+
+        fn peek(&self, address: u32) -> u8 {
+            self.memory.peek(address)
+        }
+    }
+
+    #[test]
+    fn block_transfer_length_zero_moves_64k() {
+        // TII $4000,$4000,#$0000
+        let mut cpu = HuC6280::with_bus(CountingBus {
+            memory: WideMemory::new(),
+            writes: 0,
+        });
+        cpu.write_slice(&[0x73, 0x00, 0x40, 0x00, 0x40, 0x00, 0x00], 0x0000);
+        cpu.reset_to(0xe000, 0);
+        cpu.memory.writes = 0;
+
+        assert_eq!(cpu.step().unwrap(), 17 + 6 * 0x10000);
+        // Three pushes, 64K stores and no pulls
+        assert_eq!(cpu.memory.writes, 3 + 0x10000);
+        assert_eq!(cpu.registers.program_counter, 0xe007);
+    }
+
+    #[test]
+    fn memory_operation_flag_redirects_to_zero_page() {
+        // SET; ADC #$01; SET; AND #$0F; SET; EOR #$FF; SET; ORA #$80
+        let mut cpu = cpu_at(&[
+            0xf4, 0x69, 0x01, 0xf4, 0x29, 0x0f, 0xf4, 0x49, 0xff, 0xf4, 0x09, 0x80,
+        ]);
+        cpu.registers.accumulator = 0x55;
+        cpu.registers.x = 0x10;
+        poke(&mut cpu, 0x2010, 0x41);
+
+        let mut results = Vec::new();
+        for _ in 0..4 {
+            cpu.step().unwrap();
+            assert!(cpu.memory_operation);
+            assert_eq!(cpu.step().unwrap(), 5);
+            assert!(!cpu.memory_operation);
+            results.push(cpu.peek_byte(0x2010));
+        }
+        assert_eq!(results, [0x42, 0x02, 0xfd, 0xfd]);
+        assert_eq!(cpu.registers.accumulator, 0x55);
+        assert!(cpu.status_flags.negative);
+    }
+
+    #[test]
+    fn memory_operation_flag_lasts_one_instruction() {
+        // SET; NOP; ORA #$01
+        let mut cpu = cpu_at(&[0xf4, 0xea, 0x09, 0x01]);
+        cpu.registers.x = 0x10;
+
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.step().unwrap(), 2);
+        assert_eq!(cpu.registers.accumulator, 0x01);
+        assert_eq!(cpu.peek_byte(0x2010), 0x00);
+    }
+
+    #[test]
+    fn vdc_stores_write_the_video_ports() {
+        // ST0 #$05; ST1 #$06; ST2 #$07
+        let mut cpu = cpu_at(&[0x03, 0x05, 0x13, 0x06, 0x23, 0x07]);
+        for _ in 0..3 {
+            cpu.step().unwrap();
+        }
+        assert_eq!(cpu.memory[0x1fe000], 0x05);
+        assert_eq!(cpu.memory[0x1fe002], 0x06);
+        assert_eq!(cpu.memory[0x1fe003], 0x07);
+    }
+
+    #[test]
+    fn swaps_exchange_registers() {
+        // SAX; SAY; SXY
+        let mut cpu = cpu_at(&[0x22, 0x42, 0x02]);
+        cpu.registers.accumulator = 0x01;
+        cpu.registers.x = 0x02;
+        cpu.registers.y = 0x03;
+
+        let mut registers = Vec::new();
+        for _ in 0..3 {
+            cpu.step().unwrap();
+            registers.push((cpu.registers.accumulator, cpu.registers.x, cpu.registers.y));
+        }
+        assert_eq!(registers, [(2, 1, 3), (3, 1, 2), (3, 2, 1)]);
+    }
+
+    #[test]
+    fn speed_instructions_switch_clock() {
+        // CSH; CSL
+        let mut cpu = cpu_at(&[0xd4, 0x54]);
+        cpu.step().unwrap();
+        assert!(cpu.high_speed);
+        cpu.step().unwrap();
+        assert!(!cpu.high_speed);
+    }
+
+    #[test]
+    fn branch_subroutine_returns_after_itself() {
+        // BSR +$10
+        let mut cpu = cpu_at(&[0x44, 0x10]);
+        // RTS
+        poke(&mut cpu, 0xe012, 0x60);
+
+        assert_eq!(cpu.step().unwrap(), 8);
+        assert_eq!(cpu.registers.program_counter, 0xe012);
+        assert_eq!(cpu.registers.stack_pointer, 0xfd);
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.program_counter, 0xe002);
+    }
+}
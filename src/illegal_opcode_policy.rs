@@ -0,0 +1,11 @@
+// What the CPU does with opcodes outside the documented instruction set:
+// the undocumented NMOS instructions and the unofficial NOPs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IllegalOpcodePolicy {
+    #[default]
+    Execute,
+    Error,
+    NoOperation,
+    Jam,
+    Handler,
+}
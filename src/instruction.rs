@@ -56,4 +56,82 @@ pub enum Instruction {
     TransferXToAccumulator,     //TXA transfer X to accumulator
     TransferXToStackPointer,    //TXS transfer X to stack pointer
     TransferYToAccumulator,     //TYA transfer Y to accumulator
+
+    AndAndRotateRight,            //ARR and (with accumulator) then rotate right
+    AndAndShiftRight,             //ALR and (with accumulator) then shift right
+    AndWithCarry,                 //ANC and (with accumulator), bit 7 into carry
+    AndXAndImmediate,             //ANE transfer X to accumulator and with immediate
+    AndXAndSubtract,              //SBX accumulator and X minus immediate into X
+    DecrementAndCompare,          //DCP decrement then compare with accumulator
+    IncrementAndSubtract,         //ISC increment then subtract with carry
+    Jam,                          //JAM halt the processor until reset
+    LoadAccumulatorAndX,          //LAX load accumulator and X
+    LoadAccumulatorAndXImmediate, //LXA load accumulator and X with immediate
+    LoadStackPointerAnd,          //LAS load accumulator, X and stack pointer
+    RotateLeftAndAnd,             //RLA rotate left then and with accumulator
+    RotateRightAndAdd,            //RRA rotate right then add with carry
+    ShiftLeftAndOr,               //SLO shift left then or with accumulator
+    ShiftRightAndExclusiveOr,     //SRE shift right then exclusive or with accumulator
+    StoreAccumulatorAndX,         //SAX store accumulator and X
+    StoreAccumulatorAndXAndHigh,  //SHA store accumulator and X and high byte + 1
+    StoreXAndHigh,                //SHX store X and high byte + 1
+    StoreYAndHigh,                //SHY store Y and high byte + 1
+    TransferAndXToStackPointer,   //TAS accumulator and X to stack pointer, store and high byte + 1
+
+    BranchAlways,         //BRA branch always
+    BranchIfBitReset(u8), //BBR branch on bit reset
+    BranchIfBitSet(u8),   //BBS branch on bit set
+    PullX,                //PLX pull X
+    PullY,                //PLY pull Y
+    PushX,                //PHX push X
+    PushY,                //PHY push Y
+    ResetMemoryBit(u8),   //RMB reset memory bit
+    SetMemoryBit(u8),     //SMB set memory bit
+    Stop,                 //STP stop the processor until reset
+    StoreZero,            //STZ store zero
+    TestAndResetBits,     //TRB test and reset bits
+    TestAndSetBits,       //TSB test and set bits
+    WaitForInterrupt,     //WAI wait for interrupt
+
+    BlockMoveNegative,                 //MVN block move, incrementing addresses
+    BlockMovePositive,                 //MVP block move, decrementing addresses
+    CoProcessor,                       //COP co-processor interrupt
+    ExchangeBAndA,                     //XBA exchange high and low accumulator bytes
+    ExchangeCarryAndEmulation,         //XCE exchange carry and emulation flags
+    JumpSubroutineLong,                //JSL jump subroutine long
+    PullDataBank,                      //PLB pull data bank register
+    PullDirectPage,                    //PLD pull direct page register
+    PushDataBank,                      //PHB push data bank register
+    PushDirectPage,                    //PHD push direct page register
+    PushEffectiveAddress,              //PEA/PEI/PER push effective address
+    PushProgramBank,                   //PHK push program bank register
+    ResetStatusBits,                   //REP reset status bits
+    ReturnFromSubroutineLong,          //RTL return from subroutine long
+    SetStatusBits,                     //SEP set status bits
+    TransferAccumulatorToDirectPage,   //TCD transfer 16 bit accumulator to direct page
+    TransferAccumulatorToStackPointer, //TCS transfer 16 bit accumulator to stack pointer
+    TransferDirectPageToAccumulator,   //TDC transfer direct page to 16 bit accumulator
+    TransferStackPointerToAccumulator, //TSC transfer stack pointer to 16 bit accumulator
+    TransferXToY,                      //TXY transfer X to Y
+    TransferYToX,                      //TYX transfer Y to X
+
+    BranchSubroutine,           //BSR branch to subroutine
+    ChangeSpeedHigh,            //CSH change to high speed
+    ChangeSpeedLow,             //CSL change to low speed
+    ClearAccumulator,           //CLA clear accumulator
+    ClearX,                     //CLX clear X
+    ClearY,                     //CLY clear Y
+    SetMemoryOperation,         //SET set T flag, next operation works on memory
+    StoreVdc(u8),               //ST0/ST1/ST2 store immediate to VDC port
+    SwapAccumulatorAndX,        //SAX swap accumulator and X
+    SwapAccumulatorAndY,        //SAY swap accumulator and Y
+    SwapXAndY,                  //SXY swap X and Y
+    TestMemory,                 //TST test memory against immediate
+    TransferAccumulatorToMpr,   //TAM transfer accumulator to mapping registers
+    TransferAlternateIncrement, //TAI block transfer, alternate source, increment destination
+    TransferDecrementDecrement, //TDD block transfer, decrement both
+    TransferIncrementAlternate, //TIA block transfer, increment source, alternate destination
+    TransferIncrementIncrement, //TII block transfer, increment both
+    TransferIncrementNone,      //TIN block transfer, increment source only
+    TransferMprToAccumulator,   //TMA transfer mapping register to accumulator
 }
@@ -0,0 +1,166 @@
+pub type OutputCallback = Box<dyn FnMut(u8)>;
+
+// The 6510 on-chip port: data direction register at $00, data register at
+// $01. Pins configured as inputs read the level driven by the host, except
+// for floating pins, which keep the last driven level on their capacitance
+// for `fade_cycles` cycles before reading as zero.
+pub struct IoPort {
+    pub direction: u8,
+    pub data: u8,
+    pub input: u8,
+    pub floating: u8,
+    pub fade_cycles: u64,
+    pub cycles: u64,
+
+    pub output_callback: Option<OutputCallback>,
+
+    charge: u8,
+    fade_at: [u64; 8],
+    outputs: (u8, u8),
+}
+
+impl Default for IoPort {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IoPort {
+    pub fn new() -> Self {
+        Self {
+            direction: 0x00,
+            data: 0x00,
+            input: 0xff,
+            floating: 0xc0,
+            fade_cycles: 350_000,
+            cycles: 0,
+            output_callback: None,
+            charge: 0x00,
+            fade_at: [0; 8],
+            outputs: (0x00, 0x00),
+        }
+    }
+
+    pub fn set_output_callback(&mut self, fun: OutputCallback) {
+        self.output_callback = Some(fun);
+    }
+
+    pub fn set_input(&mut self, value: u8) {
+        self.input = value;
+    }
+
+    pub fn reset(&mut self) {
+        self.write(0x0000, 0x00);
+    }
+
+    pub fn read(&self, address: u16) -> u8 {
+        if address == 0x0000 {
+            return self.direction;
+        }
+
+        let driven = self.data & self.direction;
+        let input = self.input & !self.direction & !self.floating;
+        let floating = self.charged() & !self.direction & self.floating;
+        driven | input | floating
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        if address == 0x0000 {
+            let released = self.direction & !value;
+            for bit in 0..8 {
+                if released & (1 << bit) != 0 {
+                    self.fade_at[bit] = self.cycles + self.fade_cycles;
+                }
+            }
+            self.direction = value;
+        } else {
+            self.data = value;
+        }
+
+        self.charge = (self.charged() & !self.direction) | (self.data & self.direction);
+        self.update_outputs();
+    }
+
+    // Levels seen by the outside world: outputs are driven by the data
+    // register, everything else by the host.
+    pub fn pins(&self) -> u8 {
+        (self.data & self.direction) | (self.input & !self.direction)
+    }
+
+    fn charged(&self) -> u8 {
+        let mut charge = self.charge;
+        for bit in 0..8 {
+            if self.direction & (1 << bit) == 0 && self.cycles >= self.fade_at[bit] {
+                charge &= !(1 << bit);
+            }
+        }
+        charge
+    }
+
+    // The callback only fires when the port changes what it drives: the
+    // direction of a pin or the level of an output. Input changes made by
+    // the host are not reported back to it.
+    fn update_outputs(&mut self) {
+        let outputs = (self.direction, self.data & self.direction);
+        if outputs != self.outputs {
+            self.outputs = outputs;
+            let pins = self.pins();
+            if let Some(ref mut output_callback) = self.output_callback {
+                output_callback(pins)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn direction_selects_driven_and_input_bits() {
+        let mut io_port = IoPort::new();
+        io_port.set_input(0x55);
+        io_port.write(0x0000, 0x0f);
+        io_port.write(0x0001, 0xaa);
+
+        assert_eq!(io_port.read(0x0000), 0x0f);
+        // Outputs from the data register, inputs from the host and the
+        // floating bits 6 and 7 discharged.
+        assert_eq!(io_port.read(0x0001), 0x1a);
+        assert_eq!(io_port.pins(), 0x5a);
+    }
+
+    #[test]
+    fn floating_bits_fade_after_release() {
+        let mut io_port = IoPort::new();
+        io_port.write(0x0000, 0xff);
+        io_port.write(0x0001, 0xc0);
+        io_port.write(0x0000, 0x3f);
+
+        io_port.cycles = io_port.fade_cycles - 1;
+        assert_eq!(io_port.read(0x0001) & 0xc0, 0xc0);
+        io_port.cycles = io_port.fade_cycles;
+        assert_eq!(io_port.read(0x0001) & 0xc0, 0x00);
+    }
+
+    #[test]
+    fn output_callback_reports_driven_changes_only() {
+        let mut io_port = IoPort::new();
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let log = calls.clone();
+        io_port.set_output_callback(Box::new(move |pins| log.borrow_mut().push(pins)));
+
+        io_port.write(0x0000, 0x07);
+        io_port.write(0x0001, 0x05);
+        // The same levels again, a write to an input bit and a new input
+        // level leave the outputs alone.
+        io_port.write(0x0001, 0x05);
+        io_port.write(0x0001, 0x15);
+        io_port.set_input(0x00);
+        io_port.write(0x0001, 0x17);
+
+        assert_eq!(*calls.borrow(), [0xf8, 0xfd, 0x07]);
+    }
+}
@@ -1,25 +1,101 @@
+pub mod alu;
+mod bus;
+mod bus_access;
+mod call_outcome;
+mod call_result;
+mod cpu_error;
+mod hook_action;
+mod huc6280;
+mod illegal_opcode_policy;
 mod instruction;
+mod io_port;
+mod memory_map;
 mod mode;
-mod opcodes;
+pub mod opcodes;
+mod push_source;
 mod registers;
+mod replay;
+mod run_outcome;
+mod state;
 mod status_flags;
+mod step_info;
+mod variant;
+mod w65c816;
+mod w65c816_registers;
+mod wide_bus;
 
+pub use bus::{Bus, Memory};
+pub use bus_access::BusAccess;
+pub use call_outcome::CallOutcome;
+pub use call_result::CallResult;
+pub use cpu_error::CpuError;
+pub use hook_action::HookAction;
+pub use huc6280::{HuC6280, HuC6280StepCallback};
+pub use illegal_opcode_policy::IllegalOpcodePolicy;
 pub use instruction::Instruction;
+pub use io_port::{IoPort, OutputCallback};
+pub use memory_map::{MemoryMap, ReadHook, WriteHook};
 pub use mode::Mode;
 pub use opcodes::OpCode;
+pub use push_source::PushSource;
 pub use registers::Registers;
+use replay::{Access, Replay, Snapshot};
+pub use run_outcome::RunOutcome;
+pub use state::State;
 pub use status_flags::StatusFlags;
+pub use step_info::StepInfo;
+pub use variant::Variant;
+pub use w65c816::{W65C816StepCallback, W65C816};
+pub use w65c816_registers::W65C816Registers;
+pub use wide_bus::{WideBus, WideMemory};
 
-pub struct CPU {
+use std::ops::RangeInclusive;
+
+pub const NMI_VECTOR: u16 = 0xfffa;
+pub const RESET_VECTOR: u16 = 0xfffc;
+pub const IRQ_VECTOR: u16 = 0xfffe;
+pub const CALL_SENTINEL: u16 = 0xffff;
+
+pub type StepCallback<B> = Box<dyn FnMut(&CPU<B>)>;
+pub type ReadByteCallback = Box<dyn FnMut(u16)>;
+pub type WriteByteCallback = Box<dyn FnMut(u16, u8)>;
+pub type InstructionHook<B> = Box<dyn FnMut(&mut CPU<B>) -> HookAction>;
+pub type IllegalOpcodeHandler<B> = Box<dyn FnMut(&mut CPU<B>, u8) -> Result<(), CpuError>>;
+
+pub struct CPU<B: Bus = Memory> {
+    pub variant: Variant,
     pub registers: Registers,
     pub status_flags: StatusFlags,
-    pub memory: [u8; 65536],
+    pub memory: B,
+    pub memory_map: MemoryMap,
+    pub io_port: Option<IoPort>,
+    pub address_mask: u16,
     pub cycles: u64,
+    pub total_cycles: u64,
     pub current_opcode: OpCode,
+    pub state: State,
+
+    pub irq: bool,
+    pub nmi: bool,
+    pub nmi_pending: bool,
+    pub stop_on_break: bool,
+    pub magic_constant: u8,
+    pub call_cycle_budget: u64,
+    pub trace_bus_accesses: bool,
+    pub illegal_opcode_policy: IllegalOpcodePolicy,
 
-    pub step_callback: Option<Box<dyn Fn(&CPU)>>,
-    pub read_byte_callback: Option<Box<dyn Fn(u16)>>,
-    pub write_byte_callback: Option<Box<dyn Fn(u16, u8)>>,
+    pub illegal_opcode_handler: Option<IllegalOpcodeHandler<B>>,
+    pub step_callback: Option<StepCallback<B>>,
+    pub pre_instruction_hook: Option<InstructionHook<B>>,
+    pub post_instruction_hook: Option<InstructionHook<B>>,
+    pub read_byte_callback: Option<ReadByteCallback>,
+    pub write_byte_callback: Option<WriteByteCallback>,
+
+    instruction_address: u16,
+    opcode: u8,
+    polled_interrupt_disable: Option<bool>,
+    trace: StepInfo,
+    replay: Option<Replay>,
 }
 
 impl Default for CPU {
@@ -30,46 +106,194 @@ impl Default for CPU {
 
 impl CPU {
     pub fn new() -> CPU {
+        Self::with_bus(Memory::new())
+    }
+
+    pub fn with_variant(variant: Variant) -> CPU {
+        Self::with_bus_and_variant(Memory::new(), variant)
+    }
+}
+
+impl<B: Bus> CPU<B> {
+    pub fn with_bus(memory: B) -> CPU<B> {
+        Self::with_bus_and_variant(memory, Variant::default())
+    }
+
+    pub fn with_bus_and_variant(memory: B, variant: Variant) -> CPU<B> {
+        let memory_map = MemoryMap::new();
+        let io_port = variant.has_io_port().then(|| {
+            let mut io_port = IoPort::new();
+            io_port.fade_cycles = variant.io_port_fade_cycles();
+            io_port
+        });
+        let address_mask = (0xffff_u32 >> (16 - variant.address_bus_width())) as u16;
         let cycles = 0;
-        let registers = Registers::new();
+        let total_cycles = 0;
+        // The stack pointer powers up as $00, so the three stack reads of
+        // the first reset leave it at $FD.
+        let registers = Registers {
+            stack_pointer: 0x00,
+            ..Registers::new()
+        };
         let status_flags = StatusFlags::new();
         let current_opcode = None;
-        let memory = [0; 65536];
+        let state = State::Running;
 
+        let irq = false;
+        let nmi = false;
+        let nmi_pending = false;
+        let stop_on_break = false;
+        let magic_constant = 0xee;
+        let call_cycle_budget = 1_000_000;
+        let trace_bus_accesses = false;
+        let illegal_opcode_policy = IllegalOpcodePolicy::default();
+
+        let illegal_opcode_handler = None;
         let step_callback = None;
+        let pre_instruction_hook = None;
+        let post_instruction_hook = None;
         let read_byte_callback = None;
         let write_byte_callback = None;
 
+        let instruction_address = 0;
+        let opcode = 0;
+        let polled_interrupt_disable = None;
+        let trace = StepInfo::default();
+        let replay = None;
+
         CPU {
+            variant,
             registers,
             memory,
+            memory_map,
+            io_port,
+            address_mask,
             cycles,
+            total_cycles,
             status_flags,
             current_opcode,
+            state,
+            irq,
+            nmi,
+            nmi_pending,
+            stop_on_break,
+            magic_constant,
+            call_cycle_budget,
+            trace_bus_accesses,
+            illegal_opcode_policy,
+            illegal_opcode_handler,
             step_callback,
+            pre_instruction_hook,
+            post_instruction_hook,
             read_byte_callback,
             write_byte_callback,
+            instruction_address,
+            opcode,
+            polled_interrupt_disable,
+            trace,
+            replay,
         }
     }
 
-    pub fn set_step_callback(&mut self, fun: Box<dyn Fn(&CPU)>) {
+    pub fn set_step_callback(&mut self, fun: StepCallback<B>) {
         self.step_callback = Some(fun);
     }
 
-    pub fn set_write_byte_callback(&mut self, fun: Box<dyn Fn(u16, u8)>) {
+    pub fn set_pre_instruction_hook(&mut self, fun: InstructionHook<B>) {
+        self.pre_instruction_hook = Some(fun);
+    }
+
+    pub fn set_post_instruction_hook(&mut self, fun: InstructionHook<B>) {
+        self.post_instruction_hook = Some(fun);
+    }
+
+    pub fn set_write_byte_callback(&mut self, fun: WriteByteCallback) {
         self.write_byte_callback = Some(fun);
     }
 
-    pub fn set_read_byte_callback(&mut self, fun: Box<dyn Fn(u16)>) {
+    pub fn set_read_byte_callback(&mut self, fun: ReadByteCallback) {
         self.read_byte_callback = Some(fun);
     }
 
+    pub fn add_read_hook(&mut self, range: RangeInclusive<u16>, fun: ReadHook) {
+        self.memory_map.add_read_hook(range, fun);
+    }
+
+    pub fn add_write_hook(&mut self, range: RangeInclusive<u16>, fun: WriteHook) {
+        self.memory_map.add_write_hook(range, fun);
+    }
+
+    pub fn set_stop_on_break(&mut self, stop: bool) {
+        self.stop_on_break = stop;
+    }
+
+    pub fn set_magic_constant(&mut self, value: u8) {
+        self.magic_constant = value;
+    }
+
+    pub fn set_call_cycle_budget(&mut self, cycles: u64) {
+        self.call_cycle_budget = cycles;
+    }
+
+    pub fn set_trace_bus_accesses(&mut self, trace: bool) {
+        self.trace_bus_accesses = trace;
+    }
+
+    pub fn set_illegal_opcode_policy(&mut self, policy: IllegalOpcodePolicy) {
+        self.illegal_opcode_policy = policy;
+    }
+
+    pub fn set_illegal_opcode_handler(&mut self, fun: IllegalOpcodeHandler<B>) {
+        self.illegal_opcode_handler = Some(fun);
+        self.illegal_opcode_policy = IllegalOpcodePolicy::Handler;
+    }
+
+    // Parts with fewer address lines mirror memory: only the low `width`
+    // bits of every address, vectors included, reach the bus.
+    pub fn set_address_bus_width(&mut self, width: u8) {
+        self.address_mask = (0xffff_u32 >> (16 - width.min(16))) as u16;
+    }
+
+    pub fn set_irq(&mut self, level: bool) {
+        self.irq = level;
+    }
+
+    pub fn set_nmi(&mut self, level: bool) {
+        if level && !self.nmi {
+            self.nmi_pending = true;
+        }
+        self.nmi = level;
+    }
+
+    pub fn is_halted(&self) -> bool {
+        matches!(self.state, State::Halted(_) | State::Stopped(_))
+    }
+
+    // Pulls the reset line. Like an interrupt with its stack writes turned
+    // into reads: SP drops by three, I is set, D is cleared on CMOS parts
+    // and PC is loaded from $FFFC. A, X, Y and the other flags keep their
+    // values, so a cold reset after `new` leaves SP at $FD. Use `reset_to`
+    // to start from cleared registers.
     pub fn reset(&mut self) {
-        let program_counter = self.read_word(0xfffc);
-        self.reset_to(program_counter, 0x00);
+        self.replay = None;
+        self.state = State::Running;
+        self.nmi_pending = false;
+        self.polled_interrupt_disable = None;
+        self.registers.stack_pointer = self.registers.stack_pointer.wrapping_sub(3);
+        self.status_flags.interrupt = true;
+        if self.variant.is_cmos() {
+            self.status_flags.decimal = false;
+        }
+        if let Some(ref mut io_port) = self.io_port {
+            io_port.reset();
+        }
+        self.registers.program_counter = self.read_word(RESET_VECTOR);
     }
 
     pub fn reset_to(&mut self, program_counter: u16, accumulator: u8) {
+        self.replay = None;
+        self.state = State::Running;
+        self.polled_interrupt_disable = None;
         self.registers = Registers::new();
         self.status_flags = StatusFlags::new();
 
@@ -77,41 +301,366 @@ impl CPU {
         self.registers.program_counter = program_counter;
     }
 
-    pub fn step(&mut self) -> u64 {
+    // Runs whole instructions until at least `cycles` cycles have passed and
+    // reports how many cycles the last instruction ran over, so the caller
+    // can take them off the next slice. A hook returning Stop ends the run
+    // early and the outcome tells how many cycles were run.
+    pub fn run_cycles(&mut self, cycles: u64) -> Result<RunOutcome, CpuError> {
+        let start = self.total_cycles;
+        let end = start.saturating_add(cycles);
+        while self.total_cycles < end {
+            if self.step()?.stopped {
+                return Ok(RunOutcome::Stopped(self.total_cycles - start));
+            }
+        }
+        Ok(RunOutcome::Finished(self.total_cycles - end))
+    }
+
+    // Steps until `predicate` holds before an instruction, a hook returns
+    // Stop or the CPU halts, and returns the number of cycles spent.
+    pub fn run_until<F: FnMut(&CPU<B>) -> bool>(
+        &mut self,
+        mut predicate: F,
+    ) -> Result<u64, CpuError> {
+        let start = self.total_cycles;
+        while !predicate(self) && !self.is_halted() {
+            if self.step()?.stopped {
+                break;
+            }
+        }
+        Ok(self.total_cycles - start)
+    }
+
+    pub fn run_to(&mut self, program_counter: u16) -> Result<u64, CpuError> {
+        self.run_until(|cpu| cpu.registers.program_counter == program_counter)
+    }
+
+    // Runs the subroutine at `address` as if called by a JSR from just below
+    // CALL_SENTINEL, until an RTS or RTI unwinds the stack past the pushed
+    // return address, or `call_cycle_budget` cycles have been spent. A
+    // halted CPU stays halted and the call returns at once. The return
+    // address is stored straight into memory, so it costs no bus cycles and
+    // is invisible to hooks and callbacks. An RTI pulls one byte more than
+    // the JSR frame holds, so a routine ending in RTI returns with the
+    // caller's flags and stack pointer and PC at CALL_SENTINEL.
+    pub fn call(&mut self, address: u16, registers: Registers) -> CallResult {
+        if self.is_halted() {
+            return CallResult {
+                outcome: CallOutcome::Halted,
+                registers: self.registers,
+                status_flags: self.status_flags,
+                cycles: 0,
+            };
+        }
+
+        self.replay = None;
+        self.state = State::Running;
+        self.polled_interrupt_disable = None;
+        self.registers = registers;
+        let status_flags = self.status_flags;
+
+        let return_address = CALL_SENTINEL.wrapping_sub(1);
+        for value in [(return_address >> 8) as u8, return_address as u8] {
+            self.memory
+                .write(0x100 | self.registers.stack_pointer as u16, value);
+            self.registers.stack_pointer = self.registers.stack_pointer.wrapping_sub(1);
+        }
+        let frame = self.registers.stack_pointer;
+        self.registers.program_counter = address;
+
+        let mut cycles = 0;
+        let outcome = loop {
+            if cycles >= self.call_cycle_budget {
+                break CallOutcome::BudgetExceeded;
+            }
+            match self.step() {
+                Ok(info) => {
+                    cycles += info.cycles;
+                    let unwound = (self.registers.stack_pointer.wrapping_sub(frame) as i8) > 0;
+                    match info.instruction {
+                        Some((Instruction::ReturnFromSubroutine, _)) if unwound => {
+                            break CallOutcome::Returned;
+                        }
+                        Some((Instruction::ReturnFromInterrupt, _)) if unwound => {
+                            self.registers.program_counter = CALL_SENTINEL;
+                            self.registers.stack_pointer = registers.stack_pointer;
+                            self.status_flags = status_flags;
+                            break CallOutcome::Returned;
+                        }
+                        _ => {}
+                    }
+                    if info.stopped {
+                        break CallOutcome::Stopped;
+                    }
+                    if self.is_halted() {
+                        break CallOutcome::Halted;
+                    }
+                }
+                Err(error) => break CallOutcome::Fault(error),
+            }
+        };
+
+        CallResult {
+            outcome,
+            registers: self.registers,
+            status_flags: self.status_flags,
+            cycles,
+        }
+    }
+
+    // The instruction hooks run around every instruction executed by step.
+    // When step finishes an instruction already started by tick, only the
+    // post-instruction hook runs.
+    pub fn step(&mut self) -> Result<StepInfo, CpuError> {
+        if let Some(ref replay) = self.replay {
+            let registers = replay.snapshot.registers;
+            let status_flags = replay.snapshot.status_flags;
+            while !self.tick()? {}
+            return Ok(self.finish_step(registers, status_flags));
+        }
+
+        self.cycles = 0;
+        let program_counter = self.registers.program_counter;
+        let action = self.pre_instruction();
+        if action != HookAction::Continue {
+            let registers = self.registers;
+            if action == HookAction::Skip {
+                self.skip_instruction();
+            }
+            return Ok(StepInfo {
+                program_counter,
+                stopped: action == HookAction::Stop,
+                registers_before: registers,
+                registers_after: self.registers,
+                status_flags_before: self.status_flags,
+                status_flags_after: self.status_flags,
+                ..StepInfo::default()
+            });
+        }
+
+        let registers = self.registers;
+        let status_flags = self.status_flags;
+        let interrupt = self.poll_interrupt();
+        if let Err(error) = self.execute(interrupt) {
+            self.registers.program_counter = registers.program_counter;
+            return Err(error);
+        }
+        Ok(self.finish_step(registers, status_flags))
+    }
+
+    fn finish_step(&mut self, registers: Registers, status_flags: StatusFlags) -> StepInfo {
+        let mut info = std::mem::take(&mut self.trace);
+        info.program_counter = registers.program_counter;
+        info.cycles = self.cycles;
+        info.registers_before = registers;
+        info.registers_after = self.registers;
+        info.status_flags_before = status_flags;
+        info.status_flags_after = self.status_flags;
+        info.stopped = self.post_instruction() == HookAction::Stop;
+        info
+    }
+
+    // Skipping takes no bus cycles: the opcode is only peeked to find how
+    // many operand bytes follow it.
+    fn skip_instruction(&mut self) {
+        let program_counter = self.registers.program_counter;
+        let length = match opcodes::decode(self.variant, self.peek_byte(program_counter)) {
+            Some((_, mode)) => 1 + mode.operand_length(),
+            None => 1,
+        };
+        self.registers.program_counter = program_counter.wrapping_add(length);
+    }
+
+    fn pre_instruction(&mut self) -> HookAction {
+        let Some(mut hook) = self.pre_instruction_hook.take() else {
+            return HookAction::Continue;
+        };
+        let action = hook(self);
+        self.pre_instruction_hook.get_or_insert(hook);
+        action
+    }
+
+    fn post_instruction(&mut self) -> HookAction {
+        let Some(mut hook) = self.post_instruction_hook.take() else {
+            return HookAction::Continue;
+        };
+        let action = hook(self);
+        self.post_instruction_hook.get_or_insert(hook);
+        action
+    }
+
+    // Advances the CPU by one clock cycle, performing exactly the bus access
+    // of that cycle. The current instruction is re-run from its starting
+    // state on every tick, with the accesses of earlier cycles served from
+    // a log, so that the instruction code can stay written as a whole.
+    // Returns true when the tick completed an instruction. On an error the
+    // CPU is left at the start of the faulting instruction. Illegal opcodes
+    // sent to an illegal opcode handler fail with UntickableOpcode and have
+    // to be run with step.
+    pub fn tick(&mut self) -> Result<bool, CpuError> {
+        let mut replay = match self.replay.take() {
+            Some(replay) => {
+                self.restore(&replay.snapshot);
+                replay
+            }
+            None => {
+                let interrupt = self.poll_interrupt();
+                Replay::new(self.snapshot(), interrupt)
+            }
+        };
+        replay.rewind();
+        let interrupt = replay.interrupt;
+        self.replay = Some(replay);
+
         self.cycles = 0;
+        let result = self.execute(interrupt);
+
+        let replay = self.replay.take().unwrap();
+        if let Err(error) = result {
+            self.restore(&replay.snapshot);
+            Err(error)
+        } else if replay.is_complete() {
+            Ok(true)
+        } else {
+            self.restore(&replay.snapshot);
+            self.replay = Some(replay);
+            Ok(false)
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            registers: self.registers,
+            status_flags: self.status_flags,
+            state: self.state,
+        }
+    }
+
+    fn restore(&mut self, snapshot: &Snapshot) {
+        self.registers = snapshot.registers;
+        self.status_flags = snapshot.status_flags;
+        self.state = snapshot.state;
+    }
+
+    // The hardware polls the interrupt lines before the last cycle of each
+    // instruction. CLI, SEI and PLP change I in that last cycle, so the
+    // poll after them still sees the old I: an IRQ waits one instruction
+    // after CLI or PLP and still gets in once after SEI.
+    fn poll_interrupt(&mut self) -> Option<u16> {
+        let interrupt_disable = self
+            .polled_interrupt_disable
+            .take()
+            .unwrap_or(self.status_flags.interrupt);
+        if self.is_halted() {
+            None
+        } else if self.nmi_pending {
+            self.state = State::Running;
+            self.nmi_pending = false;
+            Some(NMI_VECTOR)
+        } else if self.irq && self.state == State::Waiting {
+            // WAI resumes on IRQ even when interrupts are disabled.
+            self.state = State::Running;
+            (!interrupt_disable).then_some(IRQ_VECTOR)
+        } else if self.irq && !interrupt_disable {
+            Some(IRQ_VECTOR)
+        } else {
+            None
+        }
+    }
+
+    fn execute(&mut self, interrupt: Option<u16>) -> Result<(), CpuError> {
+        self.trace = StepInfo::default();
+
+        if self.is_halted() {
+            self.read_byte(0xffff);
+            return Ok(());
+        }
+
+        if self.state == State::Waiting {
+            self.dummy_read();
+            return Ok(());
+        }
+
+        if let Some(vector) = interrupt {
+            self.trace.interrupt = Some(vector);
+            self.dummy_read();
+            self.dummy_read();
+            self.interrupt(vector, PushSource::Interrupt);
+            return Ok(());
+        }
+
+        self.instruction_address = self.registers.program_counter;
         let opcode = self.read_byte_and_increment_pc();
-        self.current_opcode = opcodes::get(opcode);
+        self.opcode = opcode;
+        self.current_opcode = opcodes::decode(self.variant, opcode);
+        self.trace.opcode = Some(opcode);
+        self.trace.instruction = self.current_opcode;
+
+        if self.replay.as_ref().is_none_or(Replay::is_first_cycle) {
+            if let Some(mut step_callback) = self.step_callback.take() {
+                step_callback(self);
+                self.step_callback.get_or_insert(step_callback);
+            }
+        }
 
-        if let Some(ref step_callback) = self.step_callback {
-            step_callback(self)
+        if opcodes::is_illegal(self.variant, opcode) {
+            match self.illegal_opcode_policy {
+                IllegalOpcodePolicy::Execute => {}
+                IllegalOpcodePolicy::Error => {
+                    return Err(match self.current_opcode {
+                        Some((_, mode)) => CpuError::IllegalOpcode {
+                            program_counter: self.instruction_address,
+                            opcode,
+                            mode,
+                        },
+                        None => self.unknown_opcode(),
+                    });
+                }
+                IllegalOpcodePolicy::NoOperation => {
+                    if let Some((_, mode)) = self.current_opcode {
+                        self.get_address(mode)?;
+                    }
+                    return Ok(());
+                }
+                IllegalOpcodePolicy::Jam => {
+                    self.jam();
+                    return Ok(());
+                }
+                // The handler is opaque and runs once per instruction, so its
+                // bus accesses cannot be spread over ticks: only step runs it.
+                IllegalOpcodePolicy::Handler => {
+                    if self.replay.is_some() {
+                        return Err(CpuError::UntickableOpcode {
+                            program_counter: self.instruction_address,
+                            opcode,
+                        });
+                    }
+                    let Some(mut handler) = self.illegal_opcode_handler.take() else {
+                        return Err(self.unknown_opcode());
+                    };
+                    let result = handler(self, opcode);
+                    self.illegal_opcode_handler = Some(handler);
+                    return result;
+                }
+            }
         }
 
         if let Some((instruction, mode)) = self.current_opcode {
             match instruction {
                 Instruction::AddWithCarry => {
-                    let tmp: u16 = self.registers.accumulator as u16
-                        + self.get_address(mode) as u16
-                        + self.status_flags.carry as u16;
-                    self.status_flags.carry = tmp & 0x100 != 0;
-                    self.registers.accumulator = (tmp & 0xff) as u8;
-                    self.status_flags.zero = self.registers.accumulator == 0;
-                    self.status_flags.negative = self.registers.accumulator & 0x80 != 0;
-                    self.status_flags.overflow =
-                        (self.status_flags.carry as u16 ^ self.status_flags.negative as u16) != 0;
+                    let value = self.get_address(mode)?;
+                    self.add_with_carry(value);
                 }
                 Instruction::AndWithAccumulator => {
-                    let tmp = self.get_address(mode);
+                    let tmp = self.get_address(mode)?;
                     self.registers.accumulator &= tmp;
                     self.status_flags.zero = self.registers.accumulator == 0;
                     self.status_flags.negative = self.registers.accumulator & 0x80 != 0;
                 }
                 Instruction::ArithmeticShiftLeft => {
-                    let mut tmp = self.get_address(mode) as u16;
-                    tmp <<= 1;
-                    self.set_address(mode, tmp as u8);
-                    self.status_flags.zero = tmp == 0;
-                    self.status_flags.negative = tmp & 0x80 != 0;
-                    self.status_flags.carry = tmp & 0x100 != 0;
+                    let (address, tmp) = self.get_address_modify(mode)?;
+                    let result = alu::shift_left(&mut self.status_flags, tmp);
+                    self.set_address(address, result);
                 }
                 Instruction::BranchIfCarryClear => {
                     self.branch(!self.status_flags.carry);
@@ -138,505 +687,1980 @@ impl CPU {
                     self.branch(self.status_flags.overflow);
                 }
                 Instruction::BitSet => {
-                    let tmp = self.get_address(mode);
-                    self.status_flags.zero = (self.registers.accumulator & tmp) == 0;
-                    self.status_flags.negative = tmp & 0x80 != 0;
-                    self.status_flags.overflow = tmp & 0x40 != 0;
+                    let tmp = self.get_address(mode)?;
+                    if let Mode::Immediate = mode {
+                        self.status_flags.zero = (self.registers.accumulator & tmp) == 0;
+                    } else {
+                        alu::bit(&mut self.status_flags, self.registers.accumulator, tmp);
+                    }
                 }
                 Instruction::Break => {
-                    self.registers.program_counter = 0;
+                    if self.stop_on_break {
+                        self.registers.program_counter = 0;
+                    } else {
+                        self.read_byte_and_increment_pc();
+                        self.interrupt(IRQ_VECTOR, PushSource::Instruction);
+                    }
                 }
                 Instruction::ClearCarry => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     self.status_flags.carry = false;
                 }
                 Instruction::ClearDecimal => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     self.status_flags.decimal = false;
                 }
                 Instruction::ClearInterrupt => {
-                    self.cycles += 2;
+                    self.dummy_read();
+                    self.polled_interrupt_disable = Some(self.status_flags.interrupt);
                     self.status_flags.interrupt = false;
                 }
                 Instruction::ClearOverflow => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     self.status_flags.overflow = false;
                 }
                 Instruction::CompareWithAccumulator => {
-                    let tmp = self
-                        .registers
-                        .accumulator
-                        .wrapping_sub(self.get_address(mode));
-                    self.status_flags.zero = tmp == 0;
-                    self.status_flags.negative = tmp & 0x80 != 0;
-                    self.status_flags.carry = self.registers.accumulator >= tmp;
+                    let tmp = self.get_address(mode)?;
+                    alu::compare(&mut self.status_flags, self.registers.accumulator, tmp);
                 }
                 Instruction::CompareWithX => {
-                    let tmp = self.registers.x.wrapping_sub(self.get_address(mode));
-                    self.status_flags.zero = tmp == 0;
-                    self.status_flags.negative = tmp & 0x80 != 0;
-                    self.status_flags.carry = self.registers.x >= tmp;
+                    let tmp = self.get_address(mode)?;
+                    alu::compare(&mut self.status_flags, self.registers.x, tmp);
                 }
                 Instruction::CompareWithY => {
-                    let tmp = self.registers.y.wrapping_sub(self.get_address(mode));
-                    self.status_flags.zero = tmp == 0;
-                    self.status_flags.negative = tmp & 0x80 != 0;
-                    self.status_flags.carry = self.registers.y >= tmp;
+                    let tmp = self.get_address(mode)?;
+                    alu::compare(&mut self.status_flags, self.registers.y, tmp);
                 }
                 Instruction::Decrement => {
-                    let tmp = self.get_address(mode).wrapping_sub(1);
-                    self.set_address(mode, tmp);
+                    let (address, value) = self.get_address_modify(mode)?;
+                    let tmp = value.wrapping_sub(1);
+                    self.set_address(address, tmp);
                     self.status_flags.zero = tmp == 0;
                     self.status_flags.negative = tmp & 0x80 != 0;
                 }
                 Instruction::DecrementX => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     let tmp = self.registers.x.wrapping_sub(1);
                     self.registers.x = tmp;
                     self.status_flags.zero = tmp == 0;
                     self.status_flags.negative = tmp & 0x80 != 0;
                 }
                 Instruction::DecrementY => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     let tmp = self.registers.y.wrapping_sub(1);
                     self.registers.y = tmp;
                     self.status_flags.zero = tmp == 0;
                     self.status_flags.negative = tmp & 0x80 != 0;
                 }
                 Instruction::ExclusiveOrWithAccumulator => {
-                    let tmp = self.get_address(mode);
+                    let tmp = self.get_address(mode)?;
                     self.registers.accumulator ^= tmp;
                     self.status_flags.zero = self.registers.accumulator == 0;
                     self.status_flags.negative = self.registers.accumulator & 0x80 != 0;
                 }
                 Instruction::Increment => {
-                    let tmp = self.get_address(mode).wrapping_add(1);
-                    self.set_address(mode, tmp);
+                    let (address, value) = self.get_address_modify(mode)?;
+                    let tmp = value.wrapping_add(1);
+                    self.set_address(address, tmp);
                     self.status_flags.zero = tmp == 0;
                     self.status_flags.negative = tmp & 0x80 != 0;
                 }
                 Instruction::IncrementX => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     let tmp = self.registers.x.wrapping_add(1);
                     self.registers.x = tmp;
                     self.status_flags.zero = tmp == 0;
                     self.status_flags.negative = tmp & 0x80 != 0;
                 }
                 Instruction::IncrementY => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     let tmp = self.registers.y.wrapping_add(1);
                     self.registers.y = tmp;
                     self.status_flags.zero = tmp == 0;
                     self.status_flags.negative = tmp & 0x80 != 0;
                 }
+                Instruction::Jam => self.jam(),
                 Instruction::Jump => {
-                    self.cycles += 3;
-
-                    let address = self.read_word_and_increment_pc();
-                    match mode {
-                        Mode::Absolute => {
-                            self.registers.program_counter = address;
-                        }
-                        Mode::Indirect => {
-                            let address2 = self.read_word(address);
-                            self.registers.program_counter = address2;
-                            self.cycles += 2;
-                        }
-                        _ => panic!("Unimplemented jump addressing mode!"),
-                    }
+                    self.registers.program_counter = self.effective_address(mode, false)?;
                 }
                 Instruction::JumpSubroutine => {
-                    self.cycles += 6;
-                    self.push(((self.registers.program_counter + 1) >> 8) as u8);
-                    self.push(((self.registers.program_counter + 1) & 0xff) as u8);
-                    self.registers.program_counter = self.read_word_and_increment_pc();
+                    let low = self.read_byte_and_increment_pc() as u16;
+                    self.dummy_read_stack();
+                    self.push((self.registers.program_counter >> 8) as u8);
+                    self.push((self.registers.program_counter & 0xff) as u8);
+                    let high = self.read_byte(self.registers.program_counter);
+                    self.record_operand(high);
+                    let target = (high as u16) << 8 | low;
+                    self.trace.effective_address = Some(target);
+                    self.registers.program_counter = target;
                 }
                 Instruction::LoadAccumulator => {
-                    self.registers.accumulator = self.get_address(mode);
+                    self.registers.accumulator = self.get_address(mode)?;
                     self.status_flags.zero = self.registers.accumulator == 0;
                     self.status_flags.negative = self.registers.accumulator & 0x80 != 0;
                 }
                 Instruction::LoadX => {
-                    self.registers.x = self.get_address(mode);
+                    self.registers.x = self.get_address(mode)?;
                     self.status_flags.zero = self.registers.x == 0;
                     self.status_flags.negative = self.registers.x & 0x80 != 0;
                 }
                 Instruction::LoadY => {
-                    self.registers.y = self.get_address(mode);
+                    self.registers.y = self.get_address(mode)?;
                     self.status_flags.zero = self.registers.y == 0;
                     self.status_flags.negative = self.registers.y & 0x80 != 0;
                 }
                 Instruction::LogicalShiftRight => {
-                    let tmp = self.get_address(mode) as u16;
-                    let tmp2 = (tmp >> 1) & 0xff;
-                    self.set_address(mode, tmp2 as u8);
-                    self.status_flags.zero = tmp2 == 0;
-                    self.status_flags.negative = tmp2 & 0x80 != 0;
-                    self.status_flags.carry = tmp & 0x01 != 0;
+                    let (address, tmp) = self.get_address_modify(mode)?;
+                    let result = alu::shift_right(&mut self.status_flags, tmp);
+                    self.set_address(address, result);
                 }
                 Instruction::NoOperation => {
-                    self.cycles += 2;
+                    if self.variant.is_cmos() && opcode & 0x07 == 0x03 {
+                        return Ok(());
+                    }
+                    if self.variant.is_cmos() && opcode == 0x5c {
+                        let address = self.read_word_and_increment_pc();
+                        for _ in 0..5 {
+                            self.read_byte(0xff00 | (address & 0xff));
+                        }
+                        return Ok(());
+                    }
+                    self.get_address(mode)?;
                 }
                 Instruction::OrWithAccumulator => {
-                    let tmp = self.get_address(mode);
+                    let tmp = self.get_address(mode)?;
                     self.registers.accumulator |= tmp;
                     self.status_flags.zero = self.registers.accumulator == 0;
                     self.status_flags.negative = self.registers.accumulator & 0x80 != 0;
                 }
                 Instruction::PushAccumulator => {
-                    self.cycles += 3;
+                    self.dummy_read();
                     self.push(self.registers.accumulator);
                 }
                 Instruction::PushProcessorStatus => {
-                    self.cycles += 3;
-                    self.push(self.status_flags.to_byte());
+                    self.dummy_read();
+                    self.push(self.status_flags.to_pushed_byte(PushSource::Instruction));
                 }
                 Instruction::PullAccumulator => {
-                    self.cycles += 4;
+                    self.dummy_read();
+                    self.dummy_read_stack();
                     self.registers.accumulator = self.pop();
                     self.status_flags.zero = self.registers.accumulator == 0;
                     self.status_flags.negative = self.registers.accumulator & 0x80 != 0;
                 }
                 Instruction::PullProcessorStatus => {
-                    self.cycles += 4;
+                    self.dummy_read();
+                    self.dummy_read_stack();
                     let tmp = self.pop();
-                    self.status_flags = self.status_flags.from_byte(tmp);
+                    self.polled_interrupt_disable = Some(self.status_flags.interrupt);
+                    self.status_flags = StatusFlags::from_byte(tmp);
                 }
                 Instruction::RotateLeft => {
-                    let mut tmp = self.get_address(mode) as u16;
-                    let c = self.status_flags.carry as u16;
-                    self.status_flags.carry = (tmp & 0x80) != 0;
-                    tmp <<= 1;
-                    tmp |= c;
-                    tmp &= 0xff;
-                    self.set_address(mode, tmp as u8);
-                    self.status_flags.negative = tmp & 0x80 != 0;
-                    self.status_flags.zero = tmp == 0;
+                    let (address, tmp) = self.get_address_modify(mode)?;
+                    let result = alu::rotate_left(&mut self.status_flags, tmp);
+                    self.set_address(address, result);
                 }
                 Instruction::RotateRight => {
-                    let mut tmp = self.get_address(mode) as u16;
-                    let c = if (self.status_flags.carry as u16) != 0 {
-                        128
-                    } else {
-                        0
-                    };
-                    self.status_flags.carry = tmp & 1 == 1;
-                    tmp >>= 1;
-                    tmp |= c;
-                    self.set_address(mode, tmp as u8);
-                    self.status_flags.zero = tmp == 0;
-                    self.status_flags.negative = tmp & 0x80 != 0;
+                    let (address, tmp) = self.get_address_modify(mode)?;
+                    let result = alu::rotate_right(&mut self.status_flags, tmp);
+                    self.set_address(address, result);
+                }
+                Instruction::ReturnFromInterrupt => {
+                    self.dummy_read();
+                    self.dummy_read_stack();
+                    let tmp = self.pop();
+                    self.status_flags = StatusFlags::from_byte(tmp);
+                    let mut tmp = self.pop() as u16;
+                    tmp |= (self.pop() as u16) << 8;
+                    self.registers.program_counter = tmp;
                 }
-                Instruction::ReturnFromInterrupt | Instruction::ReturnFromSubroutine => {
-                    self.cycles += 6;
+                Instruction::ReturnFromSubroutine => {
+                    self.dummy_read();
+                    self.dummy_read_stack();
                     let mut tmp = self.pop() as u16;
                     tmp |= (self.pop() as u16) << 8;
-                    self.registers.program_counter = tmp + 1;
+                    self.registers.program_counter = tmp;
+                    self.dummy_read();
+                    self.increment_pc();
                 }
                 Instruction::SubtractWithCarry => {
-                    let tmp = self.get_address(mode) as u16 ^ 0xff;
-                    let tmp2 =
-                        self.registers.accumulator as u16 + tmp + self.status_flags.carry as u16;
-                    self.status_flags.carry = tmp2 & 0x100 != 0;
-                    self.registers.accumulator = tmp2 as u8;
-                    self.status_flags.zero = self.registers.accumulator == 0;
-                    self.status_flags.negative = self.registers.accumulator > 127;
-                    self.status_flags.overflow =
-                        self.status_flags.carry ^ self.status_flags.negative;
+                    let value = self.get_address(mode)?;
+                    self.subtract_with_carry(value);
                 }
                 Instruction::SetCarry => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     self.status_flags.carry = true;
                 }
                 Instruction::SetDecimal => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     self.status_flags.decimal = true;
                 }
                 Instruction::SetInterruptDisable => {
-                    self.cycles += 2;
+                    self.dummy_read();
+                    self.polled_interrupt_disable = Some(self.status_flags.interrupt);
                     self.status_flags.interrupt = true;
                 }
                 Instruction::StoreAccumulator => {
-                    self.put_address(mode, self.registers.accumulator);
+                    self.put_address(mode, self.registers.accumulator)?;
                 }
                 Instruction::StoreX => {
-                    self.put_address(mode, self.registers.x);
+                    self.put_address(mode, self.registers.x)?;
                 }
                 Instruction::StoreY => {
-                    self.put_address(mode, self.registers.y);
+                    self.put_address(mode, self.registers.y)?;
                 }
                 Instruction::TransferAccumulatorToX => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     self.registers.x = self.registers.accumulator;
                     self.status_flags.zero = self.registers.x == 0;
                     self.status_flags.negative = self.registers.x & 0x80 != 0;
                 }
                 Instruction::TransferAccumulatorToY => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     self.registers.y = self.registers.accumulator;
                     self.status_flags.zero = self.registers.y == 0;
                     self.status_flags.negative = self.registers.y & 0x80 != 0;
                 }
                 Instruction::TransferStackPointerToX => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     self.registers.x = self.registers.stack_pointer;
                     self.status_flags.zero = self.registers.x == 0;
                     self.status_flags.negative = self.registers.x & 0x80 != 0;
                 }
                 Instruction::TransferXToAccumulator => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     self.registers.accumulator = self.registers.x;
                     self.status_flags.zero = self.registers.accumulator == 0;
                     self.status_flags.negative = self.registers.accumulator & 0x80 != 0;
                 }
                 Instruction::TransferXToStackPointer => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     self.registers.stack_pointer = self.registers.x;
                 }
                 Instruction::TransferYToAccumulator => {
-                    self.cycles += 2;
+                    self.dummy_read();
                     self.registers.accumulator = self.registers.y;
                     self.status_flags.zero = self.registers.accumulator == 0;
                     self.status_flags.negative = self.registers.accumulator & 0x80 != 0;
                 }
+                Instruction::AndAndRotateRight => {
+                    let tmp = self.registers.accumulator & self.get_address(mode)?;
+                    let carry = self.status_flags.carry;
+                    let mut result = (tmp >> 1) | ((carry as u8) << 7);
+                    if self.status_flags.decimal && self.variant.has_decimal_mode() {
+                        self.status_flags.negative = carry;
+                        self.status_flags.zero = result == 0;
+                        self.status_flags.overflow = (tmp ^ result) & 0x40 != 0;
+                        if (tmp & 0x0f) + (tmp & 0x01) > 0x05 {
+                            result = (result & 0xf0) | (result.wrapping_add(0x06) & 0x0f);
+                        }
+                        self.status_flags.carry = (tmp & 0xf0) as u16 + (tmp & 0x10) as u16 > 0x50;
+                        if self.status_flags.carry {
+                            result = result.wrapping_add(0x60);
+                        }
+                    } else {
+                        self.status_flags.zero = result == 0;
+                        self.status_flags.negative = result & 0x80 != 0;
+                        self.status_flags.carry = result & 0x40 != 0;
+                        self.status_flags.overflow = ((result >> 6) ^ (result >> 5)) & 0x01 != 0;
+                    }
+                    self.registers.accumulator = result;
+                }
+                Instruction::AndAndShiftRight => {
+                    let tmp = self.registers.accumulator & self.get_address(mode)?;
+                    self.status_flags.carry = tmp & 0x01 != 0;
+                    self.registers.accumulator = tmp >> 1;
+                    self.status_flags.zero = self.registers.accumulator == 0;
+                    self.status_flags.negative = false;
+                }
+                Instruction::AndWithCarry => {
+                    self.registers.accumulator &= self.get_address(mode)?;
+                    self.status_flags.zero = self.registers.accumulator == 0;
+                    self.status_flags.negative = self.registers.accumulator & 0x80 != 0;
+                    self.status_flags.carry = self.status_flags.negative;
+                }
+                Instruction::AndXAndImmediate => {
+                    let tmp = self.get_address(mode)?;
+                    self.registers.accumulator =
+                        (self.registers.accumulator | self.magic_constant) & self.registers.x & tmp;
+                    self.status_flags.zero = self.registers.accumulator == 0;
+                    self.status_flags.negative = self.registers.accumulator & 0x80 != 0;
+                }
+                Instruction::AndXAndSubtract => {
+                    let value = self.get_address(mode)?;
+                    let tmp = self.registers.accumulator & self.registers.x;
+                    alu::compare(&mut self.status_flags, tmp, value);
+                    self.registers.x = tmp.wrapping_sub(value);
+                }
+                Instruction::DecrementAndCompare => {
+                    let (address, value) = self.get_address_modify(mode)?;
+                    let tmp = value.wrapping_sub(1);
+                    self.set_address(address, tmp);
+                    alu::compare(&mut self.status_flags, self.registers.accumulator, tmp);
+                }
+                Instruction::IncrementAndSubtract => {
+                    let (address, value) = self.get_address_modify(mode)?;
+                    let tmp = value.wrapping_add(1);
+                    self.set_address(address, tmp);
+                    self.subtract_with_carry(tmp);
+                }
+                Instruction::LoadAccumulatorAndX => {
+                    self.registers.accumulator = self.get_address(mode)?;
+                    self.registers.x = self.registers.accumulator;
+                    self.status_flags.zero = self.registers.accumulator == 0;
+                    self.status_flags.negative = self.registers.accumulator & 0x80 != 0;
+                }
+                Instruction::LoadAccumulatorAndXImmediate => {
+                    let tmp = self.get_address(mode)?;
+                    self.registers.accumulator =
+                        (self.registers.accumulator | self.magic_constant) & tmp;
+                    self.registers.x = self.registers.accumulator;
+                    self.status_flags.zero = self.registers.accumulator == 0;
+                    self.status_flags.negative = self.registers.accumulator & 0x80 != 0;
+                }
+                Instruction::LoadStackPointerAnd => {
+                    let tmp = self.get_address(mode)? & self.registers.stack_pointer;
+                    self.registers.accumulator = tmp;
+                    self.registers.x = tmp;
+                    self.registers.stack_pointer = tmp;
+                    self.status_flags.zero = tmp == 0;
+                    self.status_flags.negative = tmp & 0x80 != 0;
+                }
+                Instruction::RotateLeftAndAnd => {
+                    let (address, tmp) = self.get_address_modify(mode)?;
+                    let result = alu::rotate_left(&mut self.status_flags, tmp);
+                    self.set_address(address, result);
+                    self.registers.accumulator &= result;
+                    alu::set_zero_negative(&mut self.status_flags, self.registers.accumulator);
+                }
+                Instruction::RotateRightAndAdd => {
+                    let (address, tmp) = self.get_address_modify(mode)?;
+                    let result = alu::rotate_right(&mut self.status_flags, tmp);
+                    self.set_address(address, result);
+                    self.add_with_carry(result);
+                }
+                Instruction::ShiftLeftAndOr => {
+                    let (address, tmp) = self.get_address_modify(mode)?;
+                    let result = alu::shift_left(&mut self.status_flags, tmp);
+                    self.set_address(address, result);
+                    self.registers.accumulator |= result;
+                    alu::set_zero_negative(&mut self.status_flags, self.registers.accumulator);
+                }
+                Instruction::ShiftRightAndExclusiveOr => {
+                    let (address, tmp) = self.get_address_modify(mode)?;
+                    let result = alu::shift_right(&mut self.status_flags, tmp);
+                    self.set_address(address, result);
+                    self.registers.accumulator ^= result;
+                    alu::set_zero_negative(&mut self.status_flags, self.registers.accumulator);
+                }
+                Instruction::StoreAccumulatorAndX => {
+                    self.put_address(mode, self.registers.accumulator & self.registers.x)?;
+                }
+                Instruction::StoreAccumulatorAndXAndHigh => {
+                    self.store_and_high(mode, self.registers.accumulator & self.registers.x)?;
+                }
+                Instruction::StoreXAndHigh => {
+                    self.store_and_high(mode, self.registers.x)?;
+                }
+                Instruction::StoreYAndHigh => {
+                    self.store_and_high(mode, self.registers.y)?;
+                }
+                Instruction::TransferAndXToStackPointer => {
+                    self.registers.stack_pointer = self.registers.accumulator & self.registers.x;
+                    self.store_and_high(mode, self.registers.stack_pointer)?;
+                }
+                Instruction::BranchAlways => {
+                    self.branch(true);
+                }
+                Instruction::BranchIfBitReset(bit) => {
+                    let address = self.read_byte_and_increment_pc() as u16;
+                    let tmp = self.read_byte(address);
+                    self.read_byte(address);
+                    self.branch(tmp & (1 << bit) == 0);
+                }
+                Instruction::BranchIfBitSet(bit) => {
+                    let address = self.read_byte_and_increment_pc() as u16;
+                    let tmp = self.read_byte(address);
+                    self.read_byte(address);
+                    self.branch(tmp & (1 << bit) != 0);
+                }
+                Instruction::PullX => {
+                    self.dummy_read();
+                    self.dummy_read_stack();
+                    self.registers.x = self.pop();
+                    self.status_flags.zero = self.registers.x == 0;
+                    self.status_flags.negative = self.registers.x & 0x80 != 0;
+                }
+                Instruction::PullY => {
+                    self.dummy_read();
+                    self.dummy_read_stack();
+                    self.registers.y = self.pop();
+                    self.status_flags.zero = self.registers.y == 0;
+                    self.status_flags.negative = self.registers.y & 0x80 != 0;
+                }
+                Instruction::PushX => {
+                    self.dummy_read();
+                    self.push(self.registers.x);
+                }
+                Instruction::PushY => {
+                    self.dummy_read();
+                    self.push(self.registers.y);
+                }
+                Instruction::ResetMemoryBit(bit) => {
+                    let (address, value) = self.get_address_modify(mode)?;
+                    let tmp = value & !(1 << bit);
+                    self.set_address(address, tmp);
+                }
+                Instruction::SetMemoryBit(bit) => {
+                    let (address, value) = self.get_address_modify(mode)?;
+                    let tmp = value | (1 << bit);
+                    self.set_address(address, tmp);
+                }
+                Instruction::Stop => {
+                    self.dummy_read();
+                    self.dummy_read();
+                    self.state = State::Stopped(self.registers.program_counter);
+                }
+                Instruction::StoreZero => {
+                    self.put_address(mode, 0)?;
+                }
+                Instruction::TestAndResetBits => {
+                    let (address, tmp) = self.get_address_modify(mode)?;
+                    self.status_flags.zero = (self.registers.accumulator & tmp) == 0;
+                    self.set_address(address, tmp & !self.registers.accumulator);
+                }
+                Instruction::TestAndSetBits => {
+                    let (address, tmp) = self.get_address_modify(mode)?;
+                    self.status_flags.zero = (self.registers.accumulator & tmp) == 0;
+                    self.set_address(address, tmp | self.registers.accumulator);
+                }
+                Instruction::WaitForInterrupt => {
+                    self.dummy_read();
+                    self.dummy_read();
+                    self.state = State::Waiting;
+                }
+                Instruction::BlockMoveNegative
+                | Instruction::BlockMovePositive
+                | Instruction::CoProcessor
+                | Instruction::ExchangeBAndA
+                | Instruction::ExchangeCarryAndEmulation
+                | Instruction::JumpSubroutineLong
+                | Instruction::PullDataBank
+                | Instruction::PullDirectPage
+                | Instruction::PushDataBank
+                | Instruction::PushDirectPage
+                | Instruction::PushEffectiveAddress
+                | Instruction::PushProgramBank
+                | Instruction::ResetStatusBits
+                | Instruction::ReturnFromSubroutineLong
+                | Instruction::SetStatusBits
+                | Instruction::TransferAccumulatorToDirectPage
+                | Instruction::TransferAccumulatorToStackPointer
+                | Instruction::TransferDirectPageToAccumulator
+                | Instruction::TransferStackPointerToAccumulator
+                | Instruction::TransferXToY
+                | Instruction::TransferYToX
+                | Instruction::BranchSubroutine
+                | Instruction::ChangeSpeedHigh
+                | Instruction::ChangeSpeedLow
+                | Instruction::ClearAccumulator
+                | Instruction::ClearX
+                | Instruction::ClearY
+                | Instruction::SetMemoryOperation
+                | Instruction::StoreVdc(_)
+                | Instruction::SwapAccumulatorAndX
+                | Instruction::SwapAccumulatorAndY
+                | Instruction::SwapXAndY
+                | Instruction::TestMemory
+                | Instruction::TransferAccumulatorToMpr
+                | Instruction::TransferAlternateIncrement
+                | Instruction::TransferDecrementDecrement
+                | Instruction::TransferIncrementAlternate
+                | Instruction::TransferIncrementIncrement
+                | Instruction::TransferIncrementNone
+                | Instruction::TransferMprToAccumulator => {
+                    return Err(CpuError::UnsupportedInstruction {
+                        program_counter: self.instruction_address,
+                        opcode,
+                        instruction,
+                    });
+                }
             };
+            Ok(())
         } else {
-            panic!("Unknown opcode: {}", opcode);
+            Err(self.unknown_opcode())
+        }
+    }
+
+    fn jam(&mut self) {
+        self.dummy_read();
+        self.registers.program_counter = self.registers.program_counter.wrapping_sub(1);
+        self.state = State::Halted(self.registers.program_counter);
+    }
+
+    fn unknown_opcode(&self) -> CpuError {
+        CpuError::UnknownOpcode {
+            program_counter: self.instruction_address,
+            opcode: self.opcode,
+        }
+    }
+
+    fn unsupported_mode(&self, mode: Mode) -> CpuError {
+        CpuError::UnsupportedMode {
+            program_counter: self.instruction_address,
+            opcode: self.opcode,
+            mode,
         }
+    }
 
-        self.cycles
+    fn interrupt(&mut self, vector: u16, source: PushSource) {
+        self.push((self.registers.program_counter >> 8) as u8);
+        self.push((self.registers.program_counter & 0xff) as u8);
+        self.push(self.status_flags.to_pushed_byte(source));
+        self.status_flags.interrupt = true;
+        if self.variant.is_cmos() {
+            self.status_flags.decimal = false;
+        }
+        self.registers.program_counter = self.read_word(vector);
     }
 
     pub fn push(&mut self, value: u8) {
         self.write_byte(0x100 + self.registers.stack_pointer as u16, value);
-        self.registers.stack_pointer = self.registers.stack_pointer.saturating_sub(1);
+        self.registers.stack_pointer = self.registers.stack_pointer.wrapping_sub(1);
     }
 
     pub fn pop(&mut self) -> u8 {
-        self.registers.stack_pointer = self.registers.stack_pointer.saturating_add(1);
+        self.registers.stack_pointer = self.registers.stack_pointer.wrapping_add(1);
         self.read_byte(0x100 + self.registers.stack_pointer as u16)
     }
 
     pub fn branch(&mut self, condition: bool) {
-        let mut dist = self.get_address(Mode::Immediate) as i32;
-        if dist & 0x80 != 0 {
-            dist = 0 - ((!dist & 0xff) + 1);
-        }
-        let mut tmp = self.registers.program_counter as i32 + dist;
-        if tmp < 0 {
-            tmp += 65536;
-        }
+        let dist = self.read_byte_and_increment_pc() as i8;
+        let tmp = self.registers.program_counter.wrapping_add(dist as u16);
+        self.trace.branch_taken = condition;
+        self.trace.effective_address = Some(tmp);
 
         if condition {
-            self.cycles +=
-                ((self.registers.program_counter & 0x100) != (tmp as u16 & 0x100)) as u64;
-            self.registers.program_counter = tmp as u16;
+            self.dummy_read();
+            if (self.registers.program_counter & 0xff00) != (tmp & 0xff00) {
+                self.read_byte((self.registers.program_counter & 0xff00) | (tmp & 0xff));
+            }
+            self.registers.program_counter = tmp;
+        }
+    }
+
+    // The 65C02 spends an extra cycle in decimal mode to produce valid N
+    // and Z flags.
+    fn add_with_carry(&mut self, value: u8) {
+        let a = self.registers.accumulator;
+        let flags = &mut self.status_flags;
+        self.registers.accumulator = if flags.decimal && self.variant.is_cmos() {
+            let result = alu::add_decimal_cmos(flags, a, value);
+            self.dummy_read();
+            result
+        } else if flags.decimal && self.variant.has_decimal_mode() {
+            alu::add_decimal(flags, a, value)
+        } else {
+            alu::add(flags, a, value)
+        };
+    }
+
+    fn subtract_with_carry(&mut self, value: u8) {
+        let a = self.registers.accumulator;
+        let flags = &mut self.status_flags;
+        self.registers.accumulator = if flags.decimal && self.variant.is_cmos() {
+            let result = alu::subtract_decimal_cmos(flags, a, value);
+            self.dummy_read();
+            result
+        } else if flags.decimal && self.variant.has_decimal_mode() {
+            alu::subtract_decimal(flags, a, value)
+        } else {
+            alu::subtract(flags, a, value)
+        };
+    }
+
+    pub fn read_word(&mut self, address: u16) -> u16 {
+        self.read_byte(address) as u16 | (self.read_byte(address.wrapping_add(1)) as u16) << 8
+    }
+
+    // Pointers in the zero page wrap around from $FF to $00.
+    fn read_word_zero_page(&mut self, address: u8) -> u16 {
+        self.read_byte(address as u16) as u16
+            | (self.read_byte(address.wrapping_add(1) as u16) as u16) << 8
+    }
+
+    pub fn read_byte(&mut self, address: u16) -> u8 {
+        match self.replay.as_mut().map(Replay::advance) {
+            Some(Access::Replayed(value)) => {
+                self.cycles += 1;
+                self.record_access(address, value, false);
+                value
+            }
+            Some(Access::Skipped) => 0,
+            Some(Access::Performed) | None => {
+                let address = address & self.address_mask;
+                self.cycles += 1;
+                self.total_cycles += 1;
+                if let Some(ref mut read_byte_callback) = self.read_byte_callback {
+                    read_byte_callback(address)
+                }
+                let mut value = match self.memory_map.read(address) {
+                    Some(value) => value,
+                    None => self.memory.read(address),
+                };
+                if let Some(ref mut io_port) = self.io_port {
+                    io_port.cycles += 1;
+                    if address < 0x0002 {
+                        value = io_port.read(address);
+                    }
+                }
+                if let Some(ref mut replay) = self.replay {
+                    replay.record(value);
+                }
+                self.record_access(address, value, false);
+                value
+            }
         }
     }
 
-    pub fn read_word(&self, address: u16) -> u16 {
-        self.read_byte(address) as u16 | (self.read_byte(address + 1) as u16) << 8
+    pub fn peek_word(&self, address: u16) -> u16 {
+        self.peek_byte(address) as u16 | (self.peek_byte(address.wrapping_add(1)) as u16) << 8
     }
 
-    pub fn read_byte(&self, address: u16) -> u8 {
-        if let Some(ref read_byte_callback) = self.read_byte_callback {
-            read_byte_callback(address)
+    pub fn peek_byte(&self, address: u16) -> u8 {
+        let address = address & self.address_mask;
+        match self.io_port {
+            Some(ref io_port) if address < 0x0002 => io_port.read(address),
+            _ => self.memory.peek(address),
         }
-        self.memory[address as usize]
     }
 
     pub fn read_word_and_increment_pc(&mut self) -> u16 {
         let val = self.read_word(self.registers.program_counter);
-        self.registers.program_counter += 2;
+        self.registers.program_counter = self.registers.program_counter.wrapping_add(2);
+        self.record_operand(val as u8);
+        self.record_operand((val >> 8) as u8);
         val
     }
 
     pub fn read_byte_and_increment_pc(&mut self) -> u8 {
         let mem = self.read_byte(self.registers.program_counter);
         self.increment_pc();
+        self.record_operand(mem);
         mem
     }
 
-    fn write_byte(&mut self, address: u16, value: u8) {
-        if let Some(ref write_byte_callback) = self.write_byte_callback {
-            write_byte_callback(address, value)
+    // Operand bytes are the bytes fetched from the instruction stream after
+    // the opcode.
+    fn record_operand(&mut self, value: u8) {
+        let trace = &mut self.trace;
+        if trace.opcode.is_some() && trace.operand_count < trace.operands.len() {
+            trace.operands[trace.operand_count] = value;
+            trace.operand_count += 1;
         }
-        self.memory[address as usize] = value;
     }
 
-    pub fn write_slice(&mut self, data: &[u8], offset: u16) {
-        self.memory[offset as usize..offset as usize + data.len()].copy_from_slice(data);
+    fn record_access(&mut self, address: u16, value: u8, write: bool) {
+        if self.trace_bus_accesses {
+            self.trace.accesses.push(BusAccess {
+                address: address & self.address_mask,
+                value,
+                write,
+            });
+        }
+    }
+
+    fn write_byte(&mut self, address: u16, value: u8) {
+        match self.replay.as_mut().map(Replay::advance) {
+            Some(Access::Replayed(_)) => {
+                self.cycles += 1;
+                self.record_access(address, value, true);
+            }
+            Some(Access::Skipped) => {}
+            Some(Access::Performed) | None => {
+                let address = address & self.address_mask;
+                self.cycles += 1;
+                self.total_cycles += 1;
+                if let Some(ref mut write_byte_callback) = self.write_byte_callback {
+                    write_byte_callback(address, value)
+                }
+                if !self.memory_map.write(address, value) {
+                    self.memory.write(address, value);
+                }
+                if let Some(ref mut io_port) = self.io_port {
+                    io_port.cycles += 1;
+                    if address < 0x0002 {
+                        io_port.write(address, value);
+                    }
+                }
+                if let Some(ref mut replay) = self.replay {
+                    replay.record(value);
+                }
+                self.record_access(address, value, true);
+            }
+        }
+    }
+
+    pub fn write_slice(&mut self, data: &[u8], offset: u16) {
+        self.memory.write_slice(data, offset);
     }
 
     fn increment_pc(&mut self) {
-        self.registers.program_counter += 1;
+        self.registers.program_counter = self.registers.program_counter.wrapping_add(1);
     }
 
-    fn get_address(&mut self, mode: Mode) -> u8 {
-        match mode {
-            Mode::Implied => {
-                self.cycles += 2;
-                0
-            }
-            Mode::Immediate => {
-                self.cycles += 2;
-                self.read_byte_and_increment_pc()
-            }
-            Mode::Absolute => {
-                self.cycles += 4;
-                let address = self.read_word_and_increment_pc();
-                self.read_byte(address)
-            }
+    fn dummy_read(&mut self) {
+        self.read_byte(self.registers.program_counter);
+    }
+
+    fn dummy_read_stack(&mut self) {
+        self.read_byte(0x100 + self.registers.stack_pointer as u16);
+    }
+
+    fn effective_address(&mut self, mode: Mode, write: bool) -> Result<u16, CpuError> {
+        let address = match mode {
+            Mode::Absolute => self.read_word_and_increment_pc(),
             Mode::AbsoluteX => {
-                self.cycles += 4;
                 let address = self.read_word_and_increment_pc();
-                let address2 = address + self.registers.x as u16;
-                if (address2 & 0xff00) != (address & 0xff00) {
-                    self.cycles += 1
-                };
-                self.read_byte(address2)
+                let address2 = address.wrapping_add(self.registers.x as u16);
+                if write || (address2 & 0xff00) != (address & 0xff00) {
+                    self.fix_up_read(address, address2);
+                }
+                address2
             }
             Mode::AbsoluteY => {
-                self.cycles += 4;
                 let address = self.read_word_and_increment_pc();
-                let address2 = address + self.registers.y as u16;
-                if (address2 & 0xff00) != (address & 0xff00) {
-                    self.cycles += 1
-                };
-                self.read_byte(address2)
-            }
-            Mode::ZeroPage => {
-                self.cycles += 3;
-                let address = self.read_byte_and_increment_pc() as u16;
-                self.read_byte(address)
+                let address2 = address.wrapping_add(self.registers.y as u16);
+                if write || (address2 & 0xff00) != (address & 0xff00) {
+                    self.fix_up_read(address, address2);
+                }
+                address2
             }
+            Mode::ZeroPage => self.read_byte_and_increment_pc() as u16,
             Mode::ZeroPageX => {
-                self.cycles += 4;
-                let address = self.read_byte_and_increment_pc() as u16 + self.registers.x as u16;
-                self.read_byte(address & 0xff)
+                let address = self.read_byte_and_increment_pc() as u16;
+                self.read_byte(address);
+                (address + self.registers.x as u16) & 0xff
             }
             Mode::ZeroPageY => {
-                self.cycles += 4;
-                let address = self.read_byte_and_increment_pc() as u16 + self.registers.y as u16;
-                self.read_byte(address & 0xff)
+                let address = self.read_byte_and_increment_pc() as u16;
+                self.read_byte(address);
+                (address + self.registers.y as u16) & 0xff
             }
             Mode::IndirectY => {
-                self.cycles += 5;
-                let mut address = self.read_byte_and_increment_pc() as u16;
-                let address2 = self.read_word(address);
-                address = address2 + self.registers.y as u16;
-                if (address2 & 0xff00) != (address & 0xff00) {
-                    self.cycles += 1
+                let address = self.read_byte_and_increment_pc();
+                let address2 = self.read_word_zero_page(address);
+                let address3 = address2.wrapping_add(self.registers.y as u16);
+                if write || (address3 & 0xff00) != (address2 & 0xff00) {
+                    self.fix_up_read(address2, address3);
                 }
-                self.read_byte(address)
+                address3
             }
             Mode::XIndirect => {
-                self.cycles += 6;
+                let address = self.read_byte_and_increment_pc();
+                self.read_byte(address as u16);
+                self.read_word_zero_page(address.wrapping_add(self.registers.x))
+            }
+            Mode::ZeroPageIndirect => {
+                let address = self.read_byte_and_increment_pc();
+                self.read_word_zero_page(address)
+            }
+            Mode::Indirect => {
+                let address = self.read_word_and_increment_pc();
+                if self.variant.has_jmp_indirect_bug() {
+                    let low = self.read_byte(address) as u16;
+                    let high = self.read_byte((address & 0xff00) | (address.wrapping_add(1) & 0xff))
+                        as u16;
+                    high << 8 | low
+                } else {
+                    self.read_byte(self.registers.program_counter.wrapping_sub(1));
+                    self.read_word(address)
+                }
+            }
+            Mode::AbsoluteXIndirect => {
+                let address = self.read_word_and_increment_pc();
+                self.read_byte(self.registers.program_counter.wrapping_sub(1));
+                self.read_word(address.wrapping_add(self.registers.x as u16))
+            }
+            _ => return Err(self.unsupported_mode(mode)),
+        };
+        self.trace.effective_address = Some(address);
+        Ok(address)
+    }
+
+    // The cycle spent fixing up the high byte of an indexed address: NMOS
+    // parts read the not yet fixed address, the 65C02 re-reads the last
+    // operand byte instead.
+    fn fix_up_read(&mut self, address: u16, address2: u16) {
+        if self.variant.is_cmos() {
+            self.read_byte(self.registers.program_counter.wrapping_sub(1));
+        } else {
+            self.read_byte((address & 0xff00) | (address2 & 0xff));
+        }
+    }
 
-                let mut address = self.read_byte_and_increment_pc() as u16;
-                address += self.registers.x as u16;
-                let address2 = self.read_word(address & 0xff);
-                self.read_byte(address2)
+    fn get_address(&mut self, mode: Mode) -> Result<u8, CpuError> {
+        Ok(match mode {
+            Mode::Implied => {
+                self.dummy_read();
+                0
             }
             Mode::Accumulator => {
-                self.cycles += 2;
+                self.dummy_read();
                 self.registers.accumulator
             }
-            _ => panic!("Unimplemented get_address addressing mode!"),
-        }
+            Mode::Immediate => self.read_byte_and_increment_pc(),
+            _ => {
+                let address = self.effective_address(mode, false)?;
+                self.read_byte(address)
+            }
+        })
     }
 
-    fn set_address(&mut self, mode: Mode, value: u8) {
-        match mode {
-            Mode::Absolute => {
-                self.cycles += 2;
-                let address = self.read_word(self.registers.program_counter - 2);
-                self.write_byte(address, value);
+    // Read half of a read-modify-write instruction. The address is None
+    // for the accumulator. NMOS parts write the unmodified value back
+    // before the result is stored by set_address, the 65C02 reads it a
+    // second time. The 65C02 also skips the fix-up cycle of abs,X shifts
+    // and rotates when no page is crossed.
+    fn get_address_modify(&mut self, mode: Mode) -> Result<(Option<u16>, u8), CpuError> {
+        Ok(match mode {
+            Mode::Accumulator => {
+                self.dummy_read();
+                (None, self.registers.accumulator)
             }
-            Mode::AbsoluteX => {
-                self.cycles += 3;
-                let address = self.read_word(self.registers.program_counter - 2);
-                let address2 = address + self.registers.x as u16;
-                if (address2 & 0xff00) != (address & 0xff00) {
-                    self.cycles -= 1;
+            _ => {
+                let fix_up = !self.variant.is_cmos()
+                    || matches!(
+                        self.current_opcode,
+                        Some((Instruction::Increment | Instruction::Decrement, _))
+                    );
+                let address = self.effective_address(mode, fix_up)?;
+                let value = self.read_byte(address);
+                if self.variant.is_cmos() {
+                    self.read_byte(address);
+                } else {
+                    self.write_byte(address, value);
                 }
-                self.write_byte(address2, value);
-            }
-            Mode::ZeroPage => {
-                self.cycles += 2;
-                let address = self.read_byte(self.registers.program_counter - 1) as u16;
-                self.write_byte(address, value);
+                (Some(address), value)
             }
-            Mode::ZeroPageX => {
-                self.cycles += 2;
-                let mut address = self.read_byte(self.registers.program_counter - 1) as u16;
-                address += self.registers.x as u16;
-                self.write_byte(address & 0xff, value);
-            }
-            Mode::Accumulator => {
-                self.registers.accumulator = value;
+        })
+    }
+
+    fn set_address(&mut self, address: Option<u16>, value: u8) {
+        match address {
+            Some(address) => self.write_byte(address, value),
+            None => self.registers.accumulator = value,
+        }
+    }
+
+    // SHA, SHX, SHY and TAS store the value and'ed with the high byte of
+    // the base address plus one; on a page crossing that value also
+    // replaces the high byte of the target address.
+    fn store_and_high(&mut self, mode: Mode, value: u8) -> Result<(), CpuError> {
+        let (address, index) = match mode {
+            Mode::AbsoluteX => (self.read_word_and_increment_pc(), self.registers.x),
+            Mode::AbsoluteY => (self.read_word_and_increment_pc(), self.registers.y),
+            Mode::IndirectY => {
+                let address = self.read_byte_and_increment_pc();
+                (self.read_word_zero_page(address), self.registers.y)
             }
-            _ => panic!("Unimplemented set_address addressing mode!"),
+            _ => return Err(self.unsupported_mode(mode)),
+        };
+        let value = value & ((address >> 8) as u8).wrapping_add(1);
+        let mut address2 = address.wrapping_add(index as u16);
+        self.read_byte((address & 0xff00) | (address2 & 0xff));
+        if (address2 & 0xff00) != (address & 0xff00) {
+            address2 = (value as u16) << 8 | (address2 & 0xff);
         }
+        self.trace.effective_address = Some(address2);
+        self.write_byte(address2, value);
+        Ok(())
     }
 
-    fn put_address(&mut self, mode: Mode, value: u8) {
+    fn put_address(&mut self, mode: Mode, value: u8) -> Result<(), CpuError> {
         match mode {
-            Mode::Absolute => {
-                self.cycles += 4;
-                let address = self.read_word_and_increment_pc();
-                self.write_byte(address, value);
-            }
-            Mode::AbsoluteX => {
-                self.cycles += 4;
-                let address = self.read_word_and_increment_pc();
-                let address2 = address + self.registers.x as u16;
-                self.write_byte(address2, value);
-            }
-            Mode::AbsoluteY => {
-                self.cycles += 4;
-                let address = self.read_word_and_increment_pc();
-                let address2 = address + self.registers.y as u16;
-                if (address2 & 0xff00) != (address & 0xff00) {
-                    self.cycles += 1
-                };
-                self.write_byte(address2, value);
+            Mode::Accumulator => {
+                self.registers.accumulator = value;
             }
-            Mode::ZeroPage => {
-                self.cycles += 3;
-                let address = self.read_byte_and_increment_pc() as u16;
+            _ => {
+                let address = self.effective_address(mode, true)?;
                 self.write_byte(address, value);
             }
-            Mode::ZeroPageX => {
-                self.cycles += 4;
-                let mut address = self.read_byte_and_increment_pc() as u16;
-                address += self.registers.x as u16;
-                self.write_byte(address & 0xff, value);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpu_at(variant: Variant, program: &[u8]) -> CPU {
+        let mut cpu = CPU::with_variant(variant);
+        cpu.write_slice(program, 0x1000);
+        cpu.memory[RESET_VECTOR as usize] = 0x00;
+        cpu.memory[RESET_VECTOR as usize + 1] = 0x10;
+        cpu.reset();
+        cpu
+    }
+
+    #[test]
+    fn narrow_address_bus_mirrors_memory() {
+        for variant in [Variant::Mos6507, Variant::Mos6504] {
+            let mut cpu = CPU::with_variant(variant);
+            // LDA $F010; STA $E020
+            cpu.write_slice(&[0xad, 0x10, 0xf0, 0x8d, 0x20, 0xe0], 0x1234);
+            cpu.memory[0x1ffc] = 0x34;
+            cpu.memory[0x1ffd] = 0x12;
+            cpu.memory[0x1010] = 0x5a;
+            cpu.reset();
+            assert_eq!(cpu.registers.program_counter, 0x1234);
+            cpu.step().unwrap();
+            assert_eq!(cpu.registers.accumulator, 0x5a);
+            cpu.step().unwrap();
+            assert_eq!(cpu.memory[0x0020], 0x5a);
+            assert_eq!(cpu.memory[0xe020], 0x00);
+        }
+    }
+
+    #[test]
+    fn set_address_bus_width_changes_mirroring() {
+        let mut cpu = CPU::with_variant(Variant::Mos6507);
+        cpu.set_address_bus_width(16);
+        cpu.memory[0xfffc] = 0x00;
+        cpu.memory[0xfffd] = 0x10;
+        cpu.reset();
+        assert_eq!(cpu.registers.program_counter, 0x1000);
+    }
+
+    fn jump_indirect_page_boundary(variant: Variant) -> (u16, u64) {
+        let mut cpu = cpu_at(variant, &[0x6c, 0xff, 0x02]);
+        cpu.memory[0x02ff] = 0x34;
+        cpu.memory[0x0200] = 0x12;
+        cpu.memory[0x0300] = 0x56;
+        let info = cpu.step().unwrap();
+        (cpu.registers.program_counter, info.cycles)
+    }
+
+    #[test]
+    fn nmos_jump_indirect_wraps_within_page() {
+        assert_eq!(jump_indirect_page_boundary(Variant::Mos6510), (0x1234, 5));
+    }
+
+    #[test]
+    fn cmos_jump_indirect_crosses_page() {
+        assert_eq!(jump_indirect_page_boundary(Variant::Cmos65C02), (0x5634, 6));
+    }
+
+    // Every operand points at $1080, directly or through the pointer at $80,
+    // so an index of $FF crosses into the next page and an index of 0 does
+    // not. Branches are given the flags that leave them untaken, and BRA a
+    // short forward jump.
+    fn documented_opcode_cycles(variant: Variant, opcode: u8, index: u8) -> u64 {
+        let mut cpu = cpu_at(variant, &[opcode, 0x80, 0x10]);
+        cpu.memory[0x80] = 0x80;
+        cpu.memory[0x81] = 0x10;
+        cpu.registers.x = index;
+        cpu.registers.y = index;
+        if opcode & 0x1f == 0x10 {
+            let flag = opcode & 0x20 == 0;
+            match opcode >> 6 {
+                0 => cpu.status_flags.negative = flag,
+                1 => cpu.status_flags.overflow = flag,
+                2 => cpu.status_flags.carry = flag,
+                _ => cpu.status_flags.zero = flag,
             }
-            Mode::ZeroPageY => {
-                self.cycles += 4;
-                let mut address = self.read_byte_and_increment_pc() as u16;
-                address += self.registers.y as u16;
-                self.write_byte(address & 0xff, value);
+        }
+        if variant.is_cmos() {
+            match opcode {
+                0x80 => cpu.memory[0x1001] = 0x10,
+                // BBR and BBS test the zero page byte at $80.
+                _ if opcode & 0x0f == 0x0f => {
+                    cpu.memory[0x80] = if opcode < 0x80 { 0xff } else { 0x00 }
+                }
+                _ => {}
             }
-            Mode::XIndirect => {
-                self.cycles += 6;
-                let mut address = self.read_byte_and_increment_pc() as u16;
-                address += self.registers.x as u16;
-                let address2 = self.read_word(address & 0xff);
-                self.write_byte(address2, value);
+        }
+        cpu.step().unwrap().cycles
+    }
+
+    #[test]
+    fn documented_opcodes_match_cycle_table() {
+        let documented: Vec<u8> = (0..=255u8)
+            .filter(|&opcode| !opcodes::is_illegal(Variant::Nmos6502, opcode))
+            .collect();
+        assert_eq!(documented.len(), 151);
+
+        for opcode in documented {
+            let base = opcodes::cycles(opcode) as u64;
+            let penalty = opcodes::page_crossing_penalty(opcode) as u64;
+            let cycles = |index| documented_opcode_cycles(Variant::Nmos6502, opcode, index);
+            assert_eq!(cycles(0), base, "{opcode:#04x}");
+            assert_eq!(cycles(0xff), base + penalty, "{opcode:#04x}");
+        }
+    }
+
+    #[test]
+    fn cmos_documented_opcodes_match_cycle_table() {
+        let documented: Vec<u8> = (0..=255u8)
+            .filter(|&opcode| !opcodes::is_illegal(Variant::Cmos65C02, opcode))
+            .collect();
+        assert_eq!(documented.len(), 212);
+
+        for opcode in documented {
+            let base = opcodes::cycles_cmos(opcode) as u64;
+            let penalty = opcodes::page_crossing_penalty_cmos(opcode) as u64;
+            let cycles = |index| documented_opcode_cycles(Variant::Cmos65C02, opcode, index);
+            assert_eq!(cycles(0), base, "{opcode:#04x}");
+            assert_eq!(cycles(0xff), base + penalty, "{opcode:#04x}");
+        }
+    }
+
+    #[test]
+    fn stores_pay_no_page_crossing_penalty() {
+        // STA $10FF,Y
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x99, 0xff, 0x10]);
+        assert_eq!(cpu.step().unwrap().cycles, 5);
+        cpu.registers.program_counter = 0x1000;
+        cpu.registers.y = 0x01;
+        assert_eq!(cpu.step().unwrap().cycles, 5);
+
+        // STA ($80),Y with the pointer at $10FF
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x91, 0x80]);
+        cpu.memory[0x80] = 0xff;
+        cpu.memory[0x81] = 0x10;
+        assert_eq!(cpu.step().unwrap().cycles, 6);
+        cpu.registers.program_counter = 0x1000;
+        cpu.registers.y = 0x01;
+        assert_eq!(cpu.step().unwrap().cycles, 6);
+    }
+
+    #[test]
+    fn taken_branch_costs_extra_cycles() {
+        // BNE +$10 stays on page $10.
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xd0, 0x10]);
+        assert_eq!(cpu.step().unwrap().cycles, 3);
+        assert_eq!(cpu.registers.program_counter, 0x1012);
+
+        // BNE -$10 lands on page $0F.
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xd0, 0xf0]);
+        assert_eq!(cpu.step().unwrap().cycles, 4);
+        assert_eq!(cpu.registers.program_counter, 0x0ff2);
+
+        // BEQ is not taken while Z is clear.
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xf0, 0xf0]);
+        assert_eq!(cpu.step().unwrap().cycles, 2);
+        assert_eq!(cpu.registers.program_counter, 0x1002);
+    }
+
+    // Runs one instruction a tick at a time, checking that every tick makes
+    // exactly one bus access, and returns the accesses traced for it.
+    fn ticked_accesses(cpu: &mut CPU) -> Vec<(u16, u8, bool)> {
+        cpu.set_trace_bus_accesses(true);
+        loop {
+            let total_cycles = cpu.total_cycles;
+            let complete = cpu.tick().unwrap();
+            assert_eq!(cpu.total_cycles, total_cycles + 1);
+            if complete {
+                break;
             }
-            Mode::IndirectY => {
-                self.cycles += 5;
-                let mut address = self.read_byte_and_increment_pc() as u16;
-                let address2 = self.read_word(address);
-                address = address2 + self.registers.y as u16;
-                self.write_byte(address, value);
+        }
+        let accesses = cpu.trace.accesses.iter();
+        accesses
+            .map(|access| (access.address, access.value, access.write))
+            .collect()
+    }
+
+    #[test]
+    fn tick_performs_read_modify_write_double_write() {
+        // INC $10FF,X
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xfe, 0xff, 0x10]);
+        cpu.registers.x = 0x01;
+        cpu.memory[0x1100] = 0x41;
+        assert_eq!(
+            ticked_accesses(&mut cpu),
+            [
+                (0x1000, 0xfe, false),
+                (0x1001, 0xff, false),
+                (0x1002, 0x10, false),
+                (0x1000, 0xfe, false),
+                (0x1100, 0x41, false),
+                (0x1100, 0x41, true),
+                (0x1100, 0x42, true),
+            ]
+        );
+        assert_eq!(cpu.memory[0x1100], 0x42);
+        assert_eq!(cpu.registers.program_counter, 0x1003);
+    }
+
+    #[test]
+    fn tick_performs_dummy_read_on_page_crossing() {
+        // LDA $10FF,X
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xbd, 0xff, 0x10]);
+        cpu.registers.x = 0x01;
+        cpu.memory[0x1100] = 0x55;
+        assert_eq!(
+            ticked_accesses(&mut cpu),
+            [
+                (0x1000, 0xbd, false),
+                (0x1001, 0xff, false),
+                (0x1002, 0x10, false),
+                (0x1000, 0xbd, false),
+                (0x1100, 0x55, false),
+            ]
+        );
+        assert_eq!(cpu.registers.accumulator, 0x55);
+    }
+
+    #[test]
+    fn tick_performs_taken_branch_reads() {
+        // BNE -$10
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xd0, 0xf0, 0xea]);
+        cpu.memory[0x10f2] = 0x11;
+        assert_eq!(
+            ticked_accesses(&mut cpu),
+            [
+                (0x1000, 0xd0, false),
+                (0x1001, 0xf0, false),
+                (0x1002, 0xea, false),
+                (0x10f2, 0x11, false),
+            ]
+        );
+        assert_eq!(cpu.registers.program_counter, 0x0ff2);
+    }
+
+    #[test]
+    fn skipped_instruction_advances_program_counter() {
+        // LDA #$01; LDX $1234; INY
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xa9, 0x01, 0xae, 0x34, 0x12, 0xc8]);
+        cpu.set_pre_instruction_hook(Box::new(|cpu| {
+            if cpu.registers.program_counter < 0x1005 {
+                HookAction::Skip
+            } else {
+                HookAction::Continue
             }
-            Mode::Accumulator => {
-                self.registers.accumulator = value;
+        }));
+
+        let info = cpu.step().unwrap();
+        assert_eq!((info.cycles, cpu.registers.program_counter), (0, 0x1002));
+        cpu.run_to(0x1005).unwrap();
+        assert_eq!(cpu.step().unwrap().cycles, 2);
+        assert_eq!((cpu.registers.accumulator, cpu.registers.x), (0, 0));
+        assert_eq!(cpu.registers.y, 1);
+    }
+
+    fn cpu_with_handler(program: &[u8]) -> CPU {
+        let mut cpu = cpu_at(Variant::Nmos6502, program);
+        cpu.memory[IRQ_VECTOR as usize + 1] = 0x20;
+        cpu.memory[NMI_VECTOR as usize + 1] = 0x30;
+        // INX; RTI
+        cpu.write_slice(&[0xe8, 0x40], 0x2000);
+        // INY; RTI
+        cpu.write_slice(&[0xc8, 0x40], 0x3000);
+        cpu
+    }
+
+    #[test]
+    fn irq_entry_takes_seven_cycles_and_rti_restores_status() {
+        // NOP
+        let mut cpu = cpu_with_handler(&[0xea]);
+        cpu.status_flags.interrupt = false;
+        cpu.status_flags.carry = true;
+        cpu.set_irq(true);
+
+        let info = cpu.step().unwrap();
+        assert_eq!(info.interrupt, Some(IRQ_VECTOR));
+        assert_eq!(info.cycles, 7);
+        assert_eq!(cpu.registers.program_counter, 0x2000);
+        assert!(cpu.status_flags.interrupt);
+        assert_eq!(cpu.memory[0x01fb..0x01fe], [0x21, 0x00, 0x10]);
+
+        cpu.set_irq(false);
+        cpu.step().unwrap();
+        cpu.status_flags.carry = false;
+        assert_eq!(cpu.step().unwrap().cycles, 6);
+        assert_eq!(cpu.registers.program_counter, 0x1000);
+        assert!(cpu.status_flags.carry);
+        assert!(!cpu.status_flags.interrupt);
+    }
+
+    #[test]
+    fn interrupt_disable_masks_irq_but_not_nmi() {
+        // NOP; NOP
+        let mut cpu = cpu_with_handler(&[0xea, 0xea]);
+        cpu.set_irq(true);
+        assert_eq!(cpu.step().unwrap().interrupt, None);
+        assert_eq!(cpu.registers.program_counter, 0x1001);
+
+        cpu.set_nmi(true);
+        let info = cpu.step().unwrap();
+        assert_eq!(info.interrupt, Some(NMI_VECTOR));
+        assert_eq!(info.cycles, 7);
+        assert_eq!(cpu.registers.program_counter, 0x3000);
+    }
+
+    #[test]
+    fn nmi_wins_over_irq() {
+        // NOP
+        let mut cpu = cpu_with_handler(&[0xea]);
+        cpu.status_flags.interrupt = false;
+        cpu.set_irq(true);
+        cpu.set_nmi(true);
+        assert_eq!(cpu.step().unwrap().interrupt, Some(NMI_VECTOR));
+        // The IRQ is still pending, but the NMI handler runs with I set.
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.y, 1);
+        cpu.step().unwrap();
+        assert_eq!(cpu.step().unwrap().interrupt, Some(IRQ_VECTOR));
+    }
+
+    #[test]
+    fn nmi_triggers_on_rising_edge() {
+        // NOP; NOP; NOP
+        let mut cpu = cpu_with_handler(&[0xea, 0xea, 0xea]);
+        cpu.write_slice(&[0xea, 0xea, 0xea], 0x3000);
+        cpu.set_nmi(true);
+        assert_eq!(cpu.step().unwrap().interrupt, Some(NMI_VECTOR));
+
+        // Holding the line low does not trigger again.
+        cpu.set_nmi(true);
+        assert_eq!(cpu.step().unwrap().interrupt, None);
+
+        cpu.set_nmi(false);
+        assert_eq!(cpu.step().unwrap().interrupt, None);
+        cpu.set_nmi(true);
+        assert_eq!(cpu.step().unwrap().interrupt, Some(NMI_VECTOR));
+    }
+
+    #[test]
+    fn irq_waits_one_instruction_after_cli_and_plp() {
+        // CLI; INY; NOP
+        let mut cpu = cpu_with_handler(&[0x58, 0xc8, 0xea]);
+        cpu.set_irq(true);
+        cpu.step().unwrap();
+        assert_eq!(cpu.step().unwrap().interrupt, None);
+        assert_eq!(cpu.registers.y, 1);
+        assert_eq!(cpu.step().unwrap().interrupt, Some(IRQ_VECTOR));
+
+        // PLP; INY; NOP with a status byte that clears I
+        let mut cpu = cpu_with_handler(&[0x28, 0xc8, 0xea]);
+        cpu.push(0x00);
+        cpu.set_irq(true);
+        cpu.step().unwrap();
+        assert_eq!(cpu.step().unwrap().interrupt, None);
+        assert_eq!(cpu.registers.y, 1);
+        assert_eq!(cpu.step().unwrap().interrupt, Some(IRQ_VECTOR));
+    }
+
+    #[test]
+    fn irq_gets_in_once_after_sei() {
+        // CLI; SEI; NOP
+        let mut cpu = cpu_with_handler(&[0x58, 0x78, 0xea]);
+        cpu.set_irq(true);
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert!(cpu.status_flags.interrupt);
+
+        let info = cpu.step().unwrap();
+        assert_eq!(info.interrupt, Some(IRQ_VECTOR));
+        // The pushed status already has I set.
+        assert_eq!(cpu.memory[0x01fb] & 0x04, 0x04);
+
+        // INX; RTI returns with I set, so the IRQ stays out.
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.program_counter, 0x1002);
+        assert_eq!(cpu.step().unwrap().interrupt, None);
+    }
+
+    #[test]
+    fn irq_follows_rti_that_clears_interrupt_disable() {
+        // NOP; NOP
+        let mut cpu = cpu_with_handler(&[0xea, 0xea]);
+        cpu.status_flags.interrupt = false;
+        cpu.set_irq(true);
+        assert_eq!(cpu.step().unwrap().interrupt, Some(IRQ_VECTOR));
+        cpu.step().unwrap();
+        // RTI restores I clear and the IRQ is taken straight away.
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.program_counter, 0x1000);
+        assert_eq!(cpu.step().unwrap().interrupt, Some(IRQ_VECTOR));
+        assert_eq!(cpu.registers.x, 1);
+    }
+
+    // The status byte pushed by each source, with only C set in the flags.
+    fn pushed_status(program: &[u8], interrupt: Option<u16>) -> u8 {
+        let mut cpu = cpu_with_handler(program);
+        cpu.status_flags.interrupt = false;
+        cpu.status_flags.carry = true;
+        match interrupt {
+            Some(NMI_VECTOR) => cpu.set_nmi(true),
+            Some(_) => cpu.set_irq(true),
+            None => {}
+        }
+        cpu.step().unwrap();
+        cpu.memory[0x0100 | cpu.registers.stack_pointer.wrapping_add(1) as usize]
+    }
+
+    #[test]
+    fn pushed_status_tells_instructions_from_interrupts() {
+        // PHP
+        assert_eq!(pushed_status(&[0x08], None), 0x31);
+        // BRK
+        assert_eq!(pushed_status(&[0x00, 0x00], None), 0x31);
+        // NOP, interrupted
+        assert_eq!(pushed_status(&[0xea], Some(IRQ_VECTOR)), 0x21);
+        assert_eq!(pushed_status(&[0xea], Some(NMI_VECTOR)), 0x21);
+    }
+
+    #[test]
+    fn pulled_status_ignores_break_and_bit_5() {
+        // PLP; PHP
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x28, 0x08]);
+        cpu.push(0xff);
+        cpu.step().unwrap();
+        assert_eq!(cpu.status_flags.to_byte(), 0xcf);
+        cpu.step().unwrap();
+        assert_eq!(cpu.pop(), 0xff);
+
+        // RTI
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x40]);
+        for value in [0x20, 0x00, 0x30] {
+            cpu.push(value);
+        }
+        cpu.step().unwrap();
+        assert_eq!(cpu.status_flags.to_byte(), 0x00);
+        assert_eq!(cpu.registers.program_counter, 0x2000);
+    }
+
+    #[test]
+    fn memory_hooks_sit_between_cpu_and_memory() {
+        // LDA $D012; STA $D020; STA $D021
+        let mut cpu = cpu_at(
+            Variant::Nmos6502,
+            &[0xad, 0x12, 0xd0, 0x8d, 0x20, 0xd0, 0x8d, 0x21, 0xd0],
+        );
+        cpu.memory[0xd012] = 0x11;
+        cpu.add_read_hook(0xd012..=0xd012, Box::new(|_| Some(0x80)));
+        cpu.add_write_hook(0xd020..=0xd020, Box::new(|_, _| true));
+        cpu.add_write_hook(0xd021..=0xd021, Box::new(|_, _| false));
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0x80);
+        assert!(cpu.status_flags.negative);
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.memory[0xd020], 0x00);
+        assert_eq!(cpu.memory[0xd021], 0x80);
+    }
+
+    #[test]
+    fn arr_sets_flags_from_bits_6_and_5() {
+        // ARR #$FF
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x6b, 0xff]);
+        cpu.registers.accumulator = 0xc0;
+        cpu.status_flags.carry = true;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0xe0);
+        assert!(cpu.status_flags.negative && cpu.status_flags.carry);
+        assert!(!cpu.status_flags.overflow && !cpu.status_flags.zero);
+
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x6b, 0xff]);
+        cpu.registers.accumulator = 0x40;
+        cpu.status_flags.carry = false;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0x20);
+        assert!(!cpu.status_flags.carry && cpu.status_flags.overflow);
+    }
+
+    #[test]
+    fn arr_fixes_up_decimal_result() {
+        // SED; ARR #$FF
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xf8, 0x6b, 0xff]);
+        cpu.registers.accumulator = 0x75;
+        cpu.status_flags.carry = false;
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0x90);
+        assert!(cpu.status_flags.carry && cpu.status_flags.overflow);
+        assert!(!cpu.status_flags.negative && !cpu.status_flags.zero);
+
+        // The 2A03 has no decimal mode and takes the binary path.
+        let mut cpu = cpu_at(Variant::Ricoh2A03, &[0xf8, 0x6b, 0xff]);
+        cpu.registers.accumulator = 0x75;
+        cpu.status_flags.carry = false;
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0x3a);
+    }
+
+    #[test]
+    fn sbx_subtracts_from_accumulator_and_x() {
+        // SBX #$10; SBX #$40
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xcb, 0x10, 0xcb, 0x40]);
+        cpu.registers.accumulator = 0xf0;
+        cpu.registers.x = 0x3c;
+        cpu.status_flags.carry = false;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.x, 0x20);
+        assert_eq!(cpu.registers.accumulator, 0xf0);
+        assert!(cpu.status_flags.carry && !cpu.status_flags.negative);
+
+        // Borrows regardless of the incoming carry.
+        cpu.status_flags.decimal = true;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.x, 0xe0);
+        assert!(!cpu.status_flags.carry && cpu.status_flags.negative);
+    }
+
+    #[test]
+    fn ane_and_lxa_use_magic_constant() {
+        // ANE #$FF; LXA #$FF
+        let program = [0x8b, 0xff, 0xab, 0xff];
+        let mut cpu = cpu_at(Variant::Nmos6502, &program);
+        cpu.registers.accumulator = 0x11;
+        cpu.registers.x = 0xff;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0xff);
+        cpu.registers.accumulator = 0x00;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0xee);
+        assert_eq!(cpu.registers.x, 0xee);
+
+        let mut cpu = cpu_at(Variant::Nmos6502, &program);
+        cpu.set_magic_constant(0x00);
+        cpu.registers.accumulator = 0x11;
+        cpu.registers.x = 0x3f;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0x11);
+        cpu.registers.accumulator = 0x00;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0x00);
+        assert!(cpu.status_flags.zero);
+    }
+
+    #[test]
+    fn store_and_high_corrupts_address_on_page_cross() {
+        // SHA $06F0,Y / SHX $06F0,Y / SHY $06F0,X / TAS $06F0,Y / SHA ($80),Y
+        for program in [
+            &[0x9f, 0xf0, 0x06][..],
+            &[0x9e, 0xf0, 0x06],
+            &[0x9c, 0xf0, 0x06],
+            &[0x9b, 0xf0, 0x06],
+            &[0x93, 0x80],
+        ] {
+            let mut cpu = cpu_at(Variant::Nmos6502, program);
+            cpu.memory[0x80] = 0xf0;
+            cpu.memory[0x81] = 0x06;
+            cpu.registers.accumulator = 0x0f;
+            cpu.registers.x = 0x33;
+            cpu.registers.y = 0x20;
+            if program[0] == 0x9c {
+                cpu.registers.x = 0x20;
+                cpu.registers.y = 0x03;
             }
-            _ => panic!("Unimplemented opcode!"),
+            cpu.step().unwrap();
+            // The value is and'ed with $07 and then used as the high byte.
+            assert_eq!(cpu.memory[0x0310], 0x03, "opcode {:02x}", program[0]);
+            assert_eq!(cpu.memory[0x0710], 0x00, "opcode {:02x}", program[0]);
+        }
+
+        // Without a page cross only the value is and'ed.
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x9e, 0x00, 0x06]);
+        cpu.registers.x = 0xff;
+        cpu.registers.y = 0x10;
+        cpu.step().unwrap();
+        assert_eq!(cpu.memory[0x0610], 0x07);
+    }
+
+    #[test]
+    fn tas_sets_stack_pointer() {
+        // TAS $0600,Y
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x9b, 0x00, 0x06]);
+        cpu.registers.accumulator = 0xf0;
+        cpu.registers.x = 0x3c;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.stack_pointer, 0x30);
+        assert_eq!(cpu.memory[0x0600], 0x00);
+    }
+
+    #[test]
+    fn las_ands_memory_with_stack_pointer() {
+        // LAS $2000,Y
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xbb, 0x00, 0x20]);
+        cpu.registers.y = 0x04;
+        cpu.memory[0x2004] = 0x8f;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0x8d);
+        assert_eq!(cpu.registers.x, 0x8d);
+        assert_eq!(cpu.registers.stack_pointer, 0x8d);
+        assert!(cpu.status_flags.negative && !cpu.status_flags.zero);
+    }
+
+    #[test]
+    fn program_counter_wraps_at_end_of_memory() {
+        // LDA $1234 at $FFFE, operand across the wrap
+        let mut cpu = CPU::with_variant(Variant::Nmos6502);
+        cpu.write_slice(&[0xad, 0x34, 0x12], 0xfffe);
+        cpu.memory[0x1234] = 0x5a;
+        cpu.reset_to(0xfffe, 0);
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0x5a);
+        assert_eq!(cpu.registers.program_counter, 0x0001);
+    }
+
+    #[test]
+    fn indirect_y_pointer_wraps_in_zero_page() {
+        // LDA ($FF),Y at $FFFE
+        let mut cpu = CPU::with_variant(Variant::Nmos6502);
+        cpu.write_slice(&[0xb1, 0xff], 0xfffe);
+        cpu.memory[0x00ff] = 0x00;
+        cpu.memory[0x0000] = 0x20;
+        cpu.memory[0x2005] = 0x77;
+        cpu.reset_to(0xfffe, 0);
+        cpu.registers.y = 0x05;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.accumulator, 0x77);
+        assert_eq!(cpu.registers.program_counter, 0x0000);
+    }
+
+    #[test]
+    fn read_word_wraps_at_end_of_memory() {
+        // JMP ($FFFF); the 65C02 takes the high byte from $0000
+        let mut cpu = cpu_at(Variant::Cmos65C02, &[0x6c, 0xff, 0xff]);
+        cpu.memory[0xffff] = 0x34;
+        cpu.memory[0x0000] = 0x12;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.program_counter, 0x1234);
+    }
+
+    #[test]
+    fn subroutine_return_address_wraps() {
+        // JSR $2000 at $FFFD; RTS
+        let mut cpu = CPU::with_variant(Variant::Nmos6502);
+        cpu.write_slice(&[0x20, 0x00, 0x20], 0xfffd);
+        cpu.memory[0x2000] = 0x60;
+        cpu.reset_to(0xfffd, 0);
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.program_counter, 0x2000);
+        assert_eq!(cpu.memory[0x01fe..0x0200], [0xff, 0xff]);
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.program_counter, 0x0000);
+    }
+
+    #[test]
+    fn stack_pointer_wraps() {
+        // PHA; PLA
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x48, 0x68]);
+        cpu.registers.stack_pointer = 0x00;
+        cpu.registers.accumulator = 0x42;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.stack_pointer, 0xff);
+        assert_eq!(cpu.memory[0x0100], 0x42);
+        cpu.registers.accumulator = 0x00;
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.stack_pointer, 0x00);
+        assert_eq!(cpu.registers.accumulator, 0x42);
+    }
+
+    #[test]
+    fn cold_reset_sets_up_stack_flags_and_pc() {
+        let mut cpu = CPU::new();
+        cpu.memory[0xfffc] = 0x34;
+        cpu.memory[0xfffd] = 0x12;
+        cpu.reset();
+        assert_eq!(cpu.registers.stack_pointer, 0xfd);
+        assert_eq!(cpu.registers.program_counter, 0x1234);
+        assert!(cpu.status_flags.interrupt);
+        assert!(!cpu.status_flags.decimal);
+    }
+
+    #[test]
+    fn warm_reset_keeps_registers_and_drops_stack_by_three() {
+        let mut cpu = cpu_at(Variant::Nmos6502, &[]);
+        cpu.registers.accumulator = 0x11;
+        cpu.registers.x = 0x22;
+        cpu.registers.y = 0x33;
+        cpu.registers.stack_pointer = 0x01;
+        cpu.status_flags.carry = true;
+        cpu.status_flags.decimal = true;
+        cpu.status_flags.interrupt = false;
+        cpu.reset();
+        assert_eq!(cpu.registers.stack_pointer, 0xfe);
+        assert_eq!(
+            (cpu.registers.accumulator, cpu.registers.x, cpu.registers.y),
+            (0x11, 0x22, 0x33)
+        );
+        assert!(cpu.status_flags.carry && cpu.status_flags.decimal);
+        assert!(cpu.status_flags.interrupt);
+        assert_eq!(cpu.registers.program_counter, 0x1000);
+
+        // reset_to still starts from cleared registers.
+        cpu.reset_to(0x2000, 0x44);
+        assert_eq!(cpu.registers.stack_pointer, 0xff);
+        assert_eq!(cpu.registers.x, 0x00);
+        assert!(!cpu.status_flags.carry && !cpu.status_flags.interrupt);
+    }
+
+    #[test]
+    fn cmos_reset_clears_decimal() {
+        for (variant, decimal) in [(Variant::Nmos6502, true), (Variant::Cmos65C02, false)] {
+            let mut cpu = cpu_at(variant, &[]);
+            cpu.status_flags.decimal = true;
+            cpu.reset();
+            assert_eq!(cpu.status_flags.decimal, decimal, "{variant:?}");
         }
     }
+
+    #[test]
+    fn cmos_branch_always() {
+        // BRA +$10
+        let mut cpu = cpu_at(Variant::Cmos65C02, &[0x80, 0x10]);
+        assert_eq!(cpu.step().unwrap().cycles, 3);
+        assert_eq!(cpu.registers.program_counter, 0x1012);
+
+        // BRA -$10
+        let mut cpu = cpu_at(Variant::Cmos65C02, &[0x80, 0xf0]);
+        assert_eq!(cpu.step().unwrap().cycles, 4);
+        assert_eq!(cpu.registers.program_counter, 0x0ff2);
+    }
+
+    #[test]
+    fn cmos_index_register_stack_operations() {
+        // PHX; PHY; PLX; PLY
+        let mut cpu = cpu_at(Variant::Cmos65C02, &[0xda, 0x5a, 0xfa, 0x7a]);
+        cpu.registers.x = 0x80;
+        cpu.registers.y = 0x00;
+        cpu.run_to(0x1002).unwrap();
+        assert_eq!(cpu.memory[0x01fc..0x01fe], [0x00, 0x80]);
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.x, 0x00);
+        assert!(cpu.status_flags.zero);
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.y, 0x80);
+        assert!(cpu.status_flags.negative && !cpu.status_flags.zero);
+    }
+
+    #[test]
+    fn cmos_store_zero() {
+        // STZ $10; STZ $10,X; STZ $2000; STZ $2000,X
+        let mut cpu = cpu_at(
+            Variant::Cmos65C02,
+            &[0x64, 0x10, 0x74, 0x10, 0x9c, 0x00, 0x20, 0x9e, 0x00, 0x20],
+        );
+        cpu.registers.accumulator = 0xff;
+        cpu.registers.x = 0x01;
+        for address in [0x10, 0x11, 0x2000, 0x2001] {
+            cpu.memory[address] = 0xaa;
+        }
+        cpu.run_to(0x100a).unwrap();
+        for address in [0x10, 0x11, 0x2000, 0x2001] {
+            assert_eq!(cpu.memory[address], 0x00, "{address:#06x}");
+        }
+    }
+
+    #[test]
+    fn cmos_test_and_set_and_reset_bits() {
+        // TSB $10; TRB $11
+        let mut cpu = cpu_at(Variant::Cmos65C02, &[0x04, 0x10, 0x14, 0x11]);
+        cpu.registers.accumulator = 0x0f;
+        cpu.memory[0x10] = 0xf0;
+        cpu.memory[0x11] = 0xff;
+
+        assert_eq!(cpu.step().unwrap().cycles, 5);
+        assert_eq!(cpu.memory[0x10], 0xff);
+        assert!(cpu.status_flags.zero);
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.memory[0x11], 0xf0);
+        assert!(!cpu.status_flags.zero);
+        assert_eq!(cpu.registers.accumulator, 0x0f);
+    }
+
+    #[test]
+    fn cmos_zero_page_bit_instructions() {
+        // SMB3 $10; BBS3 $10,+2; RMB3 $10; BBR3 $10,+2
+        let program = [
+            0xb7, 0x10, 0xbf, 0x10, 0x02, 0x00, 0x00, 0x37, 0x10, 0x3f, 0x10, 0x02,
+        ];
+        let mut cpu = cpu_at(Variant::Cmos65C02, &program);
+        cpu.memory[0x10] = 0x01;
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.memory[0x10], 0x09);
+        assert_eq!(cpu.step().unwrap().cycles, 6);
+        assert_eq!(cpu.registers.program_counter, 0x1007);
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.memory[0x10], 0x01);
+        assert_eq!(cpu.step().unwrap().cycles, 6);
+        assert_eq!(cpu.registers.program_counter, 0x100e);
+
+        // BBS0 $10,+2 is not taken while bit 0 is clear.
+        let mut cpu = cpu_at(Variant::Cmos65C02, &[0x8f, 0x10, 0x02]);
+        assert_eq!(cpu.step().unwrap().cycles, 5);
+        assert_eq!(cpu.registers.program_counter, 0x1003);
+    }
+
+    #[test]
+    fn cmos_wait_resumes_on_interrupt() {
+        // WAI; INX
+        let mut cpu = cpu_at(Variant::Cmos65C02, &[0xcb, 0xe8]);
+        cpu.step().unwrap();
+        assert_eq!(cpu.state, State::Waiting);
+        assert_eq!(cpu.step().unwrap().cycles, 1);
+        assert_eq!(cpu.registers.program_counter, 0x1001);
+
+        // With interrupts disabled the IRQ only wakes the CPU.
+        cpu.set_irq(true);
+        cpu.step().unwrap();
+        assert_eq!(cpu.state, State::Running);
+        assert_eq!(cpu.registers.x, 0x01);
+
+        // WAI
+        let mut cpu = cpu_at(Variant::Cmos65C02, &[0xcb]);
+        cpu.memory[IRQ_VECTOR as usize + 1] = 0x20;
+        cpu.status_flags.interrupt = false;
+        cpu.step().unwrap();
+        cpu.set_irq(true);
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers.program_counter, 0x2000);
+    }
+
+    #[test]
+    fn cmos_stop_halts_until_reset() {
+        // STP
+        let mut cpu = cpu_at(Variant::Cmos65C02, &[0xdb]);
+        assert_eq!(cpu.step().unwrap().cycles, 3);
+        assert!(cpu.is_halted());
+        cpu.set_nmi(true);
+        cpu.step().unwrap();
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.registers.program_counter, 0x1001);
+
+        cpu.reset();
+        assert!(!cpu.is_halted());
+        assert_eq!(cpu.registers.program_counter, 0x1000);
+    }
+
+    #[test]
+    fn cmos_zero_page_indirect() {
+        // LDA ($FF); STA ($10)
+        let mut cpu = cpu_at(Variant::Cmos65C02, &[0xb2, 0xff, 0x92, 0x10]);
+        cpu.memory[0xff] = 0x34;
+        cpu.memory[0x00] = 0x12;
+        cpu.memory[0x1234] = 0x56;
+        cpu.memory[0x10] = 0x00;
+        cpu.memory[0x11] = 0x20;
+
+        assert_eq!(cpu.step().unwrap().cycles, 5);
+        assert_eq!(cpu.registers.accumulator, 0x56);
+        cpu.step().unwrap();
+        assert_eq!(cpu.memory[0x2000], 0x56);
+    }
+
+    #[test]
+    fn cmos_bit_immediate_only_sets_zero() {
+        // BIT #$C0
+        let mut cpu = cpu_at(Variant::Cmos65C02, &[0x89, 0xc0]);
+        cpu.registers.accumulator = 0x01;
+        cpu.step().unwrap();
+        assert!(cpu.status_flags.zero);
+        assert!(!cpu.status_flags.negative && !cpu.status_flags.overflow);
+    }
+
+    #[test]
+    fn cmos_decimal_mode_sets_flags_from_result() {
+        // SED; ADC #$01; SBC #$01
+        let mut cpu = cpu_at(Variant::Cmos65C02, &[0xf8, 0x69, 0x01, 0xe9, 0x01]);
+        cpu.registers.accumulator = 0x99;
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.step().unwrap().cycles, 3);
+        assert_eq!(cpu.registers.accumulator, 0x00);
+        assert!(cpu.status_flags.zero && cpu.status_flags.carry);
+        assert!(!cpu.status_flags.negative);
+
+        assert_eq!(cpu.step().unwrap().cycles, 3);
+        assert_eq!(cpu.registers.accumulator, 0x99);
+        assert!(cpu.status_flags.negative && !cpu.status_flags.zero);
+
+        // The NMOS part takes Z from the binary sum $9A.
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xf8, 0x69, 0x01]);
+        cpu.registers.accumulator = 0x99;
+        cpu.step().unwrap();
+        assert_eq!(cpu.step().unwrap().cycles, 2);
+        assert_eq!(cpu.registers.accumulator, 0x00);
+        assert!(!cpu.status_flags.zero);
+    }
+
+    #[test]
+    fn call_pushes_return_address_without_bus_cycles() {
+        // RTS
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x60]);
+        let total_cycles = cpu.total_cycles;
+        let writes = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = writes.clone();
+        cpu.set_write_byte_callback(Box::new(move |_, _| counter.set(counter.get() + 1)));
+
+        let registers = cpu.registers;
+        let result = cpu.call(0x1000, registers);
+        assert!(matches!(result.outcome, CallOutcome::Returned));
+        assert_eq!(result.cycles, 6);
+        assert_eq!(cpu.total_cycles - total_cycles, 6);
+        assert_eq!(writes.get(), 0);
+        assert_eq!(result.registers.stack_pointer, registers.stack_pointer);
+    }
+
+    #[test]
+    fn call_returns_through_rti_with_caller_flags() {
+        // RTI
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x40]);
+        cpu.status_flags.carry = true;
+        let flags = cpu.status_flags.to_byte();
+
+        let registers = cpu.registers;
+        let result = cpu.call(0x1000, registers);
+        assert!(matches!(result.outcome, CallOutcome::Returned));
+        assert_eq!(result.cycles, 6);
+        assert_eq!(result.registers.program_counter, CALL_SENTINEL);
+        assert_eq!(result.registers.stack_pointer, registers.stack_pointer);
+        assert_eq!(result.status_flags.to_byte(), flags);
+        assert_eq!(cpu.status_flags.to_byte(), flags);
+    }
+
+    #[test]
+    fn call_leaves_halted_cpu_halted() {
+        // JAM
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x02]);
+        cpu.step().unwrap();
+        assert!(cpu.is_halted());
+
+        let registers = cpu.registers;
+        let result = cpu.call(0x2000, registers);
+        assert!(matches!(result.outcome, CallOutcome::Halted));
+        assert_eq!(result.cycles, 0);
+        assert!(cpu.is_halted());
+    }
+
+    #[test]
+    fn illegal_opcode_handler_is_not_ticked() {
+        // SLO $1234, handled as LDA $1234
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x0f, 0x34, 0x12]);
+        cpu.memory[0x1234] = 0x42;
+        let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = calls.clone();
+        cpu.set_illegal_opcode_handler(Box::new(move |cpu, _| {
+            counter.set(counter.get() + 1);
+            let address = cpu.read_word_and_increment_pc();
+            cpu.registers.accumulator = cpu.read_byte(address);
+            Ok(())
+        }));
+
+        assert!(matches!(
+            cpu.tick(),
+            Err(CpuError::UntickableOpcode {
+                program_counter: 0x1000,
+                opcode: 0x0f
+            })
+        ));
+        assert_eq!(calls.get(), 0);
+        assert_eq!(cpu.registers.program_counter, 0x1000);
+
+        let info = cpu.step().unwrap();
+        assert_eq!(calls.get(), 1);
+        assert_eq!(info.cycles, 4);
+        assert_eq!(cpu.registers.accumulator, 0x42);
+        assert_eq!(cpu.registers.program_counter, 0x1003);
+    }
+
+    #[test]
+    fn run_cycles_tells_stop_from_finish() {
+        // NOP; NOP; NOP
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xea, 0xea, 0xea]);
+        assert_eq!(cpu.run_cycles(3).unwrap(), RunOutcome::Finished(1));
+
+        cpu.set_post_instruction_hook(Box::new(|_| HookAction::Stop));
+        assert_eq!(cpu.run_cycles(4).unwrap(), RunOutcome::Stopped(2));
+    }
+
+    #[test]
+    fn run_cycles_without_limit_runs_until_stopped() {
+        // NOP; NOP
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0xea, 0xea]);
+        cpu.set_post_instruction_hook(Box::new(|cpu| {
+            if cpu.registers.program_counter == 0x1002 {
+                HookAction::Stop
+            } else {
+                HookAction::Continue
+            }
+        }));
+        assert_eq!(cpu.run_cycles(u64::MAX).unwrap(), RunOutcome::Stopped(4));
+    }
+
+    #[test]
+    fn run_to_ends_when_cpu_halts() {
+        // JAM
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x02]);
+        cpu.run_to(0x2000).unwrap();
+        assert!(cpu.is_halted());
+    }
+
+    #[test]
+    fn jump_subroutine_reports_target() {
+        // JSR $1234
+        let mut cpu = cpu_at(Variant::Nmos6502, &[0x20, 0x34, 0x12]);
+        let info = cpu.step().unwrap();
+        assert_eq!(info.effective_address, Some(0x1234));
+        assert_eq!(&info.operands[..info.operand_count], &[0x34, 0x12]);
+    }
+
+    #[test]
+    fn step_reports_branches_registers_flags_and_accesses() {
+        // BNE +$02; NOP; NOP; BEQ +$10; INC $2000
+        let mut cpu = cpu_at(
+            Variant::Nmos6502,
+            &[0xd0, 0x02, 0xea, 0xea, 0xf0, 0x10, 0xee, 0x00, 0x20],
+        );
+        cpu.memory[0x2000] = 0xff;
+        cpu.status_flags.zero = false;
+        cpu.trace_bus_accesses = true;
+
+        let info = cpu.step().unwrap();
+        assert!(info.branch_taken);
+        assert_eq!(info.effective_address, Some(0x1004));
+        assert_eq!(info.registers_before.program_counter, 0x1000);
+        assert_eq!(info.registers_after.program_counter, 0x1004);
+
+        let info = cpu.step().unwrap();
+        assert!(!info.branch_taken);
+        assert_eq!(info.effective_address, Some(0x1016));
+        assert_eq!(info.registers_after.program_counter, 0x1006);
+
+        let info = cpu.step().unwrap();
+        assert!(!info.branch_taken);
+        assert_eq!(info.effective_address, Some(0x2000));
+        assert!(!info.status_flags_before.zero);
+        assert!(info.status_flags_after.zero);
+        assert_eq!(
+            info.registers_before.accumulator,
+            info.registers_after.accumulator
+        );
+        let accesses: Vec<_> = info
+            .accesses
+            .iter()
+            .map(|access| (access.address, access.value, access.write))
+            .collect();
+        assert_eq!(
+            accesses,
+            [
+                (0x1006, 0xee, false),
+                (0x1007, 0x00, false),
+                (0x1008, 0x20, false),
+                (0x2000, 0xff, false),
+                (0x2000, 0xff, true),
+                (0x2000, 0x00, true),
+            ]
+        );
+    }
 }
@@ -0,0 +1,152 @@
+use std::ops::RangeInclusive;
+
+pub type ReadHook = Box<dyn FnMut(u16) -> Option<u8>>;
+pub type WriteHook = Box<dyn FnMut(u16, u8) -> bool>;
+
+// Memory-mapped I/O. Hooks are registered for an address range and found
+// through a per-page table, so pages without hooks cost a single lookup.
+// A read hook returning a value replaces the bus read, a write hook
+// returning true keeps the value from reaching the bus.
+pub struct MemoryMap {
+    read_hooks: Vec<(RangeInclusive<u16>, ReadHook)>,
+    write_hooks: Vec<(RangeInclusive<u16>, WriteHook)>,
+    read_pages: Vec<Vec<usize>>,
+    write_pages: Vec<Vec<usize>>,
+}
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryMap {
+    pub fn new() -> Self {
+        Self {
+            read_hooks: Vec::new(),
+            write_hooks: Vec::new(),
+            read_pages: vec![Vec::new(); 256],
+            write_pages: vec![Vec::new(); 256],
+        }
+    }
+
+    pub fn add_read_hook(&mut self, range: RangeInclusive<u16>, fun: ReadHook) {
+        let index = self.read_hooks.len();
+        for page in pages(&range) {
+            self.read_pages[page].push(index);
+        }
+        self.read_hooks.push((range, fun));
+    }
+
+    pub fn add_write_hook(&mut self, range: RangeInclusive<u16>, fun: WriteHook) {
+        let index = self.write_hooks.len();
+        for page in pages(&range) {
+            self.write_pages[page].push(index);
+        }
+        self.write_hooks.push((range, fun));
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn read(&mut self, address: u16) -> Option<u8> {
+        for &index in &self.read_pages[address as usize >> 8] {
+            let (ref range, ref mut fun) = self.read_hooks[index];
+            if range.contains(&address) {
+                if let Some(value) = fun(address) {
+                    return Some(value);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) -> bool {
+        let mut handled = false;
+        for &index in &self.write_pages[address as usize >> 8] {
+            let (ref range, ref mut fun) = self.write_hooks[index];
+            if range.contains(&address) {
+                handled |= fun(address, value);
+            }
+        }
+        handled
+    }
+}
+
+fn pages(range: &RangeInclusive<u16>) -> RangeInclusive<usize> {
+    (*range.start() as usize >> 8)..=(*range.end() as usize >> 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn read_hook_value_overrides_and_none_falls_through() {
+        let mut memory_map = MemoryMap::new();
+        memory_map.add_read_hook(
+            0xd000..=0xd0ff,
+            Box::new(|address| (address & 1 == 0).then_some(0x42)),
+        );
+        assert_eq!(memory_map.read(0xd010), Some(0x42));
+        assert_eq!(memory_map.read(0xd011), None);
+        assert_eq!(memory_map.read(0xd100), None);
+        assert_eq!(memory_map.read(0xcfff), None);
+    }
+
+    #[test]
+    fn first_read_hook_with_a_value_wins() {
+        let mut memory_map = MemoryMap::new();
+        memory_map.add_read_hook(0xd000..=0xd00f, Box::new(|_| None));
+        memory_map.add_read_hook(0xd000..=0xd0ff, Box::new(|_| Some(0x01)));
+        memory_map.add_read_hook(0xd000..=0xd0ff, Box::new(|_| Some(0x02)));
+        assert_eq!(memory_map.read(0xd008), Some(0x01));
+    }
+
+    #[test]
+    fn write_hooks_all_run_and_any_true_suppresses() {
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        let mut memory_map = MemoryMap::new();
+        let log = writes.clone();
+        memory_map.add_write_hook(
+            0xd400..=0xd41f,
+            Box::new(move |address, value| {
+                log.borrow_mut().push((address, value));
+                false
+            }),
+        );
+        memory_map.add_write_hook(0xd418..=0xd418, Box::new(|_, _| true));
+        assert!(!memory_map.write(0xd400, 0x11));
+        assert!(memory_map.write(0xd418, 0x0f));
+        assert!(!memory_map.write(0xd420, 0x22));
+        assert_eq!(*writes.borrow(), vec![(0xd400, 0x11), (0xd418, 0x0f)]);
+    }
+
+    #[test]
+    fn ranges_spanning_pages_cover_every_page() {
+        let mut memory_map = MemoryMap::new();
+        memory_map.add_read_hook(0x12f0..=0x1510, Box::new(|address| Some(address as u8)));
+        memory_map.add_write_hook(0xfff0..=0xffff, Box::new(|_, _| true));
+        assert_eq!(memory_map.read(0x12ef), None);
+        assert_eq!(memory_map.read(0x12f0), Some(0xf0));
+        assert_eq!(memory_map.read(0x1380), Some(0x80));
+        assert_eq!(memory_map.read(0x1400), Some(0x00));
+        assert_eq!(memory_map.read(0x1510), Some(0x10));
+        assert_eq!(memory_map.read(0x1511), None);
+        assert!(memory_map.write(0xffff, 0x00));
+        assert!(!memory_map.write(0xffef, 0x00));
+    }
+
+    #[test]
+    fn clear_removes_hooks() {
+        let mut memory_map = MemoryMap::new();
+        memory_map.add_read_hook(0x0000..=0xffff, Box::new(|_| Some(0xff)));
+        memory_map.add_write_hook(0x0000..=0xffff, Box::new(|_, _| true));
+        memory_map.clear();
+        assert_eq!(memory_map.read(0x1234), None);
+        assert!(!memory_map.write(0x1234, 0x00));
+    }
+}
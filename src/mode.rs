@@ -13,4 +13,58 @@ pub enum Mode {
     XIndirect,
     IndirectY,
     Implied,
+    ZeroPageIndirect,
+    AbsoluteXIndirect,
+    ZeroPageRelative,
+    AbsoluteLong,
+    AbsoluteLongX,
+    AbsoluteIndirectLong,
+    ZeroPageIndirectLong,
+    ZeroPageIndirectLongY,
+    StackRelative,
+    StackRelativeIndirectY,
+    RelativeLong,
+    BlockMove,
+    ImmediateZeroPage,
+    ImmediateZeroPageX,
+    ImmediateAbsolute,
+    ImmediateAbsoluteX,
+    BlockTransfer,
+}
+
+impl Mode {
+    // Number of bytes that follow the opcode in the instruction stream.
+    pub fn operand_length(self) -> u16 {
+        match self {
+            Mode::Accumulator | Mode::Implied => 0,
+            Mode::Immediate
+            | Mode::ZeroPage
+            | Mode::ZeroPageX
+            | Mode::ZeroPageY
+            | Mode::Relative
+            | Mode::XIndirect
+            | Mode::IndirectY
+            | Mode::ZeroPageIndirect
+            | Mode::ZeroPageIndirectLong
+            | Mode::ZeroPageIndirectLongY
+            | Mode::StackRelative
+            | Mode::StackRelativeIndirectY => 1,
+            Mode::Absolute
+            | Mode::AbsoluteX
+            | Mode::AbsoluteY
+            | Mode::Indirect
+            | Mode::AbsoluteXIndirect
+            | Mode::AbsoluteIndirectLong
+            | Mode::ZeroPageRelative
+            | Mode::RelativeLong
+            | Mode::BlockMove
+            | Mode::ImmediateZeroPage
+            | Mode::ImmediateZeroPageX => 2,
+            Mode::AbsoluteLong
+            | Mode::AbsoluteLongX
+            | Mode::ImmediateAbsolute
+            | Mode::ImmediateAbsoluteX => 3,
+            Mode::BlockTransfer => 6,
+        }
+    }
 }
@@ -1,267 +1,1254 @@
 use crate::instruction::Instruction;
 use crate::mode::Mode;
+use crate::variant::Variant;
 
 pub type OpCode = Option<(Instruction, Mode)>;
 
 static OP_CODES: [OpCode; 256] = [
     Some((Instruction::Break, Mode::Implied)),
     Some((Instruction::OrWithAccumulator, Mode::XIndirect)),
-    None,
-    None,
-    None,
+    Some((Instruction::Jam, Mode::Implied)),
+    Some((Instruction::ShiftLeftAndOr, Mode::XIndirect)),
+    Some((Instruction::NoOperation, Mode::ZeroPage)),
     Some((Instruction::OrWithAccumulator, Mode::ZeroPage)),
     Some((Instruction::ArithmeticShiftLeft, Mode::ZeroPage)),
-    None,
+    Some((Instruction::ShiftLeftAndOr, Mode::ZeroPage)),
     Some((Instruction::PushProcessorStatus, Mode::Implied)),
     Some((Instruction::OrWithAccumulator, Mode::Immediate)),
     Some((Instruction::ArithmeticShiftLeft, Mode::Accumulator)),
-    None,
-    None,
+    Some((Instruction::AndWithCarry, Mode::Immediate)),
+    Some((Instruction::NoOperation, Mode::Absolute)),
     Some((Instruction::OrWithAccumulator, Mode::Absolute)),
     Some((Instruction::ArithmeticShiftLeft, Mode::Absolute)),
-    None,
+    Some((Instruction::ShiftLeftAndOr, Mode::Absolute)),
     Some((Instruction::BranchIfPlus, Mode::Relative)),
     Some((Instruction::OrWithAccumulator, Mode::IndirectY)),
-    None,
-    None,
-    None,
+    Some((Instruction::Jam, Mode::Implied)),
+    Some((Instruction::ShiftLeftAndOr, Mode::IndirectY)),
+    Some((Instruction::NoOperation, Mode::ZeroPageX)),
     Some((Instruction::OrWithAccumulator, Mode::ZeroPageX)),
     Some((Instruction::ArithmeticShiftLeft, Mode::ZeroPageX)),
-    None,
+    Some((Instruction::ShiftLeftAndOr, Mode::ZeroPageX)),
     Some((Instruction::ClearCarry, Mode::Implied)),
     Some((Instruction::OrWithAccumulator, Mode::AbsoluteY)),
-    None,
-    None,
-    None,
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::ShiftLeftAndOr, Mode::AbsoluteY)),
+    Some((Instruction::NoOperation, Mode::AbsoluteX)),
     Some((Instruction::OrWithAccumulator, Mode::AbsoluteX)),
     Some((Instruction::ArithmeticShiftLeft, Mode::AbsoluteX)),
-    None,
+    Some((Instruction::ShiftLeftAndOr, Mode::AbsoluteX)),
     Some((Instruction::JumpSubroutine, Mode::Absolute)),
     Some((Instruction::AndWithAccumulator, Mode::XIndirect)),
-    None,
-    None,
+    Some((Instruction::Jam, Mode::Implied)),
+    Some((Instruction::RotateLeftAndAnd, Mode::XIndirect)),
     Some((Instruction::BitSet, Mode::ZeroPage)),
     Some((Instruction::AndWithAccumulator, Mode::ZeroPage)),
     Some((Instruction::RotateLeft, Mode::ZeroPage)),
-    None,
+    Some((Instruction::RotateLeftAndAnd, Mode::ZeroPage)),
     Some((Instruction::PullProcessorStatus, Mode::Implied)),
     Some((Instruction::AndWithAccumulator, Mode::Immediate)),
     Some((Instruction::RotateLeft, Mode::Accumulator)),
-    None,
+    Some((Instruction::AndWithCarry, Mode::Immediate)),
     Some((Instruction::BitSet, Mode::Absolute)),
     Some((Instruction::AndWithAccumulator, Mode::Absolute)),
     Some((Instruction::RotateLeft, Mode::Absolute)),
-    None,
+    Some((Instruction::RotateLeftAndAnd, Mode::Absolute)),
     Some((Instruction::BranchIfMinus, Mode::Relative)),
     Some((Instruction::AndWithAccumulator, Mode::IndirectY)),
-    None,
-    None,
-    None,
+    Some((Instruction::Jam, Mode::Implied)),
+    Some((Instruction::RotateLeftAndAnd, Mode::IndirectY)),
+    Some((Instruction::NoOperation, Mode::ZeroPageX)),
     Some((Instruction::AndWithAccumulator, Mode::ZeroPageX)),
     Some((Instruction::RotateLeft, Mode::ZeroPageX)),
-    None,
+    Some((Instruction::RotateLeftAndAnd, Mode::ZeroPageX)),
     Some((Instruction::SetCarry, Mode::Implied)),
     Some((Instruction::AndWithAccumulator, Mode::AbsoluteY)),
-    None,
-    None,
-    None,
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::RotateLeftAndAnd, Mode::AbsoluteY)),
+    Some((Instruction::NoOperation, Mode::AbsoluteX)),
     Some((Instruction::AndWithAccumulator, Mode::AbsoluteX)),
     Some((Instruction::RotateLeft, Mode::AbsoluteX)),
-    None,
+    Some((Instruction::RotateLeftAndAnd, Mode::AbsoluteX)),
     Some((Instruction::ReturnFromInterrupt, Mode::Implied)),
     Some((Instruction::ExclusiveOrWithAccumulator, Mode::XIndirect)),
-    None,
-    None,
-    None,
+    Some((Instruction::Jam, Mode::Implied)),
+    Some((Instruction::ShiftRightAndExclusiveOr, Mode::XIndirect)),
+    Some((Instruction::NoOperation, Mode::ZeroPage)),
     Some((Instruction::ExclusiveOrWithAccumulator, Mode::ZeroPage)),
     Some((Instruction::LogicalShiftRight, Mode::ZeroPage)),
-    None,
+    Some((Instruction::ShiftRightAndExclusiveOr, Mode::ZeroPage)),
     Some((Instruction::PushAccumulator, Mode::Implied)),
     Some((Instruction::ExclusiveOrWithAccumulator, Mode::Immediate)),
     Some((Instruction::LogicalShiftRight, Mode::Accumulator)),
-    None,
+    Some((Instruction::AndAndShiftRight, Mode::Immediate)),
     Some((Instruction::Jump, Mode::Absolute)),
     Some((Instruction::ExclusiveOrWithAccumulator, Mode::Absolute)),
     Some((Instruction::LogicalShiftRight, Mode::Absolute)),
-    None,
+    Some((Instruction::ShiftRightAndExclusiveOr, Mode::Absolute)),
     Some((Instruction::BranchIfOverflowClear, Mode::Relative)),
     Some((Instruction::ExclusiveOrWithAccumulator, Mode::IndirectY)),
-    None,
-    None,
-    None,
+    Some((Instruction::Jam, Mode::Implied)),
+    Some((Instruction::ShiftRightAndExclusiveOr, Mode::IndirectY)),
+    Some((Instruction::NoOperation, Mode::ZeroPageX)),
     Some((Instruction::ExclusiveOrWithAccumulator, Mode::ZeroPageX)),
     Some((Instruction::LogicalShiftRight, Mode::ZeroPageX)),
-    None,
+    Some((Instruction::ShiftRightAndExclusiveOr, Mode::ZeroPageX)),
     Some((Instruction::ClearInterrupt, Mode::Implied)),
     Some((Instruction::ExclusiveOrWithAccumulator, Mode::AbsoluteY)),
-    None,
-    None,
-    None,
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::ShiftRightAndExclusiveOr, Mode::AbsoluteY)),
+    Some((Instruction::NoOperation, Mode::AbsoluteX)),
     Some((Instruction::ExclusiveOrWithAccumulator, Mode::AbsoluteX)),
     Some((Instruction::LogicalShiftRight, Mode::AbsoluteX)),
-    None,
+    Some((Instruction::ShiftRightAndExclusiveOr, Mode::AbsoluteX)),
     Some((Instruction::ReturnFromSubroutine, Mode::Implied)),
     Some((Instruction::AddWithCarry, Mode::XIndirect)),
-    None,
-    None,
-    None,
+    Some((Instruction::Jam, Mode::Implied)),
+    Some((Instruction::RotateRightAndAdd, Mode::XIndirect)),
+    Some((Instruction::NoOperation, Mode::ZeroPage)),
     Some((Instruction::AddWithCarry, Mode::ZeroPage)),
     Some((Instruction::RotateRight, Mode::ZeroPage)),
-    None,
+    Some((Instruction::RotateRightAndAdd, Mode::ZeroPage)),
     Some((Instruction::PullAccumulator, Mode::Implied)),
     Some((Instruction::AddWithCarry, Mode::Immediate)),
     Some((Instruction::RotateRight, Mode::Accumulator)),
-    None,
+    Some((Instruction::AndAndRotateRight, Mode::Immediate)),
     Some((Instruction::Jump, Mode::Indirect)),
     Some((Instruction::AddWithCarry, Mode::Absolute)),
     Some((Instruction::RotateRight, Mode::Absolute)),
-    None,
+    Some((Instruction::RotateRightAndAdd, Mode::Absolute)),
     Some((Instruction::BranchIfOverflowSet, Mode::Relative)),
     Some((Instruction::AddWithCarry, Mode::IndirectY)),
-    None,
-    None,
-    None,
+    Some((Instruction::Jam, Mode::Implied)),
+    Some((Instruction::RotateRightAndAdd, Mode::IndirectY)),
+    Some((Instruction::NoOperation, Mode::ZeroPageX)),
     Some((Instruction::AddWithCarry, Mode::ZeroPageX)),
     Some((Instruction::RotateRight, Mode::ZeroPageX)),
-    None,
+    Some((Instruction::RotateRightAndAdd, Mode::ZeroPageX)),
     Some((Instruction::SetInterruptDisable, Mode::Implied)),
     Some((Instruction::AddWithCarry, Mode::AbsoluteY)),
-    None,
-    None,
-    None,
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::RotateRightAndAdd, Mode::AbsoluteY)),
+    Some((Instruction::NoOperation, Mode::AbsoluteX)),
     Some((Instruction::AddWithCarry, Mode::AbsoluteX)),
     Some((Instruction::RotateRight, Mode::AbsoluteX)),
-    None,
-    None,
+    Some((Instruction::RotateRightAndAdd, Mode::AbsoluteX)),
+    Some((Instruction::NoOperation, Mode::Immediate)),
     Some((Instruction::StoreAccumulator, Mode::XIndirect)),
-    None,
-    None,
+    Some((Instruction::NoOperation, Mode::Immediate)),
+    Some((Instruction::StoreAccumulatorAndX, Mode::XIndirect)),
     Some((Instruction::StoreY, Mode::ZeroPage)),
     Some((Instruction::StoreAccumulator, Mode::ZeroPage)),
     Some((Instruction::StoreX, Mode::ZeroPage)),
-    None,
+    Some((Instruction::StoreAccumulatorAndX, Mode::ZeroPage)),
     Some((Instruction::DecrementY, Mode::Implied)),
-    None,
+    Some((Instruction::NoOperation, Mode::Immediate)),
     Some((Instruction::TransferXToAccumulator, Mode::Accumulator)),
-    None,
+    Some((Instruction::AndXAndImmediate, Mode::Immediate)),
     Some((Instruction::StoreY, Mode::Absolute)),
     Some((Instruction::StoreAccumulator, Mode::Absolute)),
     Some((Instruction::StoreX, Mode::Absolute)),
-    None,
+    Some((Instruction::StoreAccumulatorAndX, Mode::Absolute)),
     Some((Instruction::BranchIfCarryClear, Mode::Relative)),
     Some((Instruction::StoreAccumulator, Mode::IndirectY)),
-    None,
-    None,
+    Some((Instruction::Jam, Mode::Implied)),
+    Some((Instruction::StoreAccumulatorAndXAndHigh, Mode::IndirectY)),
     Some((Instruction::StoreY, Mode::ZeroPageX)),
     Some((Instruction::StoreAccumulator, Mode::ZeroPageX)),
     Some((Instruction::StoreX, Mode::ZeroPageY)),
-    None,
+    Some((Instruction::StoreAccumulatorAndX, Mode::ZeroPageY)),
     Some((Instruction::TransferYToAccumulator, Mode::Implied)),
     Some((Instruction::StoreAccumulator, Mode::AbsoluteY)),
     Some((Instruction::TransferXToStackPointer, Mode::Accumulator)),
-    None,
-    None,
+    Some((Instruction::TransferAndXToStackPointer, Mode::AbsoluteY)),
+    Some((Instruction::StoreYAndHigh, Mode::AbsoluteX)),
     Some((Instruction::StoreAccumulator, Mode::AbsoluteX)),
-    None,
-    None,
+    Some((Instruction::StoreXAndHigh, Mode::AbsoluteY)),
+    Some((Instruction::StoreAccumulatorAndXAndHigh, Mode::AbsoluteY)),
     Some((Instruction::LoadY, Mode::Immediate)),
     Some((Instruction::LoadAccumulator, Mode::XIndirect)),
     Some((Instruction::LoadX, Mode::Immediate)),
-    None,
+    Some((Instruction::LoadAccumulatorAndX, Mode::XIndirect)),
     Some((Instruction::LoadY, Mode::ZeroPage)),
     Some((Instruction::LoadAccumulator, Mode::ZeroPage)),
     Some((Instruction::LoadX, Mode::ZeroPage)),
-    None,
+    Some((Instruction::LoadAccumulatorAndX, Mode::ZeroPage)),
     Some((Instruction::TransferAccumulatorToY, Mode::Implied)),
     Some((Instruction::LoadAccumulator, Mode::Immediate)),
     Some((Instruction::TransferAccumulatorToX, Mode::Accumulator)),
-    None,
+    Some((Instruction::LoadAccumulatorAndXImmediate, Mode::Immediate)),
     Some((Instruction::LoadY, Mode::Absolute)),
     Some((Instruction::LoadAccumulator, Mode::Absolute)),
     Some((Instruction::LoadX, Mode::Absolute)),
-    None,
+    Some((Instruction::LoadAccumulatorAndX, Mode::Absolute)),
     Some((Instruction::BranchIfCarrySet, Mode::Relative)),
     Some((Instruction::LoadAccumulator, Mode::IndirectY)),
-    None,
-    None,
+    Some((Instruction::Jam, Mode::Implied)),
+    Some((Instruction::LoadAccumulatorAndX, Mode::IndirectY)),
     Some((Instruction::LoadY, Mode::ZeroPageX)),
     Some((Instruction::LoadAccumulator, Mode::ZeroPageX)),
     Some((Instruction::LoadX, Mode::ZeroPageY)),
-    None,
+    Some((Instruction::LoadAccumulatorAndX, Mode::ZeroPageY)),
     Some((Instruction::ClearOverflow, Mode::Implied)),
     Some((Instruction::LoadAccumulator, Mode::AbsoluteY)),
     Some((Instruction::TransferStackPointerToX, Mode::Accumulator)),
-    None,
+    Some((Instruction::LoadStackPointerAnd, Mode::AbsoluteY)),
     Some((Instruction::LoadY, Mode::AbsoluteX)),
     Some((Instruction::LoadAccumulator, Mode::AbsoluteX)),
     Some((Instruction::LoadX, Mode::AbsoluteY)),
-    None,
+    Some((Instruction::LoadAccumulatorAndX, Mode::AbsoluteY)),
     Some((Instruction::CompareWithY, Mode::Immediate)),
     Some((Instruction::CompareWithAccumulator, Mode::XIndirect)),
-    None,
-    None,
+    Some((Instruction::NoOperation, Mode::Immediate)),
+    Some((Instruction::DecrementAndCompare, Mode::XIndirect)),
     Some((Instruction::CompareWithY, Mode::ZeroPage)),
     Some((Instruction::CompareWithAccumulator, Mode::ZeroPage)),
     Some((Instruction::Decrement, Mode::ZeroPage)),
-    None,
+    Some((Instruction::DecrementAndCompare, Mode::ZeroPage)),
     Some((Instruction::IncrementY, Mode::Implied)),
     Some((Instruction::CompareWithAccumulator, Mode::Immediate)),
     Some((Instruction::DecrementX, Mode::Accumulator)),
-    None,
+    Some((Instruction::AndXAndSubtract, Mode::Immediate)),
     Some((Instruction::CompareWithY, Mode::Absolute)),
     Some((Instruction::CompareWithAccumulator, Mode::Absolute)),
     Some((Instruction::Decrement, Mode::Absolute)),
-    None,
+    Some((Instruction::DecrementAndCompare, Mode::Absolute)),
     Some((Instruction::BranchIfNotEqual, Mode::Relative)),
     Some((Instruction::CompareWithAccumulator, Mode::IndirectY)),
-    None,
-    None,
-    None,
+    Some((Instruction::Jam, Mode::Implied)),
+    Some((Instruction::DecrementAndCompare, Mode::IndirectY)),
+    Some((Instruction::NoOperation, Mode::ZeroPageX)),
     Some((Instruction::CompareWithAccumulator, Mode::ZeroPageX)),
     Some((Instruction::Decrement, Mode::ZeroPageX)),
-    None,
+    Some((Instruction::DecrementAndCompare, Mode::ZeroPageX)),
     Some((Instruction::ClearDecimal, Mode::Implied)),
     Some((Instruction::CompareWithAccumulator, Mode::AbsoluteY)),
-    None,
-    None,
-    None,
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::DecrementAndCompare, Mode::AbsoluteY)),
+    Some((Instruction::NoOperation, Mode::AbsoluteX)),
     Some((Instruction::CompareWithAccumulator, Mode::AbsoluteX)),
     Some((Instruction::Decrement, Mode::AbsoluteX)),
-    None,
+    Some((Instruction::DecrementAndCompare, Mode::AbsoluteX)),
     Some((Instruction::CompareWithX, Mode::Immediate)),
     Some((Instruction::SubtractWithCarry, Mode::XIndirect)),
-    None,
-    None,
+    Some((Instruction::NoOperation, Mode::Immediate)),
+    Some((Instruction::IncrementAndSubtract, Mode::XIndirect)),
     Some((Instruction::CompareWithX, Mode::ZeroPage)),
     Some((Instruction::SubtractWithCarry, Mode::ZeroPage)),
     Some((Instruction::Increment, Mode::ZeroPage)),
-    None,
+    Some((Instruction::IncrementAndSubtract, Mode::ZeroPage)),
     Some((Instruction::IncrementX, Mode::Implied)),
     Some((Instruction::SubtractWithCarry, Mode::Immediate)),
     Some((Instruction::NoOperation, Mode::Accumulator)),
-    None,
+    Some((Instruction::SubtractWithCarry, Mode::Immediate)),
+    Some((Instruction::CompareWithX, Mode::Absolute)),
+    Some((Instruction::SubtractWithCarry, Mode::Absolute)),
+    Some((Instruction::Increment, Mode::Absolute)),
+    Some((Instruction::IncrementAndSubtract, Mode::Absolute)),
+    Some((Instruction::BranchIfEqual, Mode::Relative)),
+    Some((Instruction::SubtractWithCarry, Mode::IndirectY)),
+    Some((Instruction::Jam, Mode::Implied)),
+    Some((Instruction::IncrementAndSubtract, Mode::IndirectY)),
+    Some((Instruction::NoOperation, Mode::ZeroPageX)),
+    Some((Instruction::SubtractWithCarry, Mode::ZeroPageX)),
+    Some((Instruction::Increment, Mode::ZeroPageX)),
+    Some((Instruction::IncrementAndSubtract, Mode::ZeroPageX)),
+    Some((Instruction::SetDecimal, Mode::Implied)),
+    Some((Instruction::SubtractWithCarry, Mode::AbsoluteY)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::IncrementAndSubtract, Mode::AbsoluteY)),
+    Some((Instruction::NoOperation, Mode::AbsoluteX)),
+    Some((Instruction::SubtractWithCarry, Mode::AbsoluteX)),
+    Some((Instruction::Increment, Mode::AbsoluteX)),
+    Some((Instruction::IncrementAndSubtract, Mode::AbsoluteX)),
+];
+
+static CMOS_OP_CODES: [OpCode; 256] = [
+    Some((Instruction::Break, Mode::Implied)),
+    Some((Instruction::OrWithAccumulator, Mode::XIndirect)),
+    Some((Instruction::NoOperation, Mode::Immediate)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::TestAndSetBits, Mode::ZeroPage)),
+    Some((Instruction::OrWithAccumulator, Mode::ZeroPage)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::ZeroPage)),
+    Some((Instruction::ResetMemoryBit(0), Mode::ZeroPage)),
+    Some((Instruction::PushProcessorStatus, Mode::Implied)),
+    Some((Instruction::OrWithAccumulator, Mode::Immediate)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::TestAndSetBits, Mode::Absolute)),
+    Some((Instruction::OrWithAccumulator, Mode::Absolute)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::Absolute)),
+    Some((Instruction::BranchIfBitReset(0), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfPlus, Mode::Relative)),
+    Some((Instruction::OrWithAccumulator, Mode::IndirectY)),
+    Some((Instruction::OrWithAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::TestAndResetBits, Mode::ZeroPage)),
+    Some((Instruction::OrWithAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::ZeroPageX)),
+    Some((Instruction::ResetMemoryBit(1), Mode::ZeroPage)),
+    Some((Instruction::ClearCarry, Mode::Implied)),
+    Some((Instruction::OrWithAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::Increment, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::TestAndResetBits, Mode::Absolute)),
+    Some((Instruction::OrWithAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitReset(1), Mode::ZeroPageRelative)),
+    Some((Instruction::JumpSubroutine, Mode::Absolute)),
+    Some((Instruction::AndWithAccumulator, Mode::XIndirect)),
+    Some((Instruction::NoOperation, Mode::Immediate)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::BitSet, Mode::ZeroPage)),
+    Some((Instruction::AndWithAccumulator, Mode::ZeroPage)),
+    Some((Instruction::RotateLeft, Mode::ZeroPage)),
+    Some((Instruction::ResetMemoryBit(2), Mode::ZeroPage)),
+    Some((Instruction::PullProcessorStatus, Mode::Implied)),
+    Some((Instruction::AndWithAccumulator, Mode::Immediate)),
+    Some((Instruction::RotateLeft, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::BitSet, Mode::Absolute)),
+    Some((Instruction::AndWithAccumulator, Mode::Absolute)),
+    Some((Instruction::RotateLeft, Mode::Absolute)),
+    Some((Instruction::BranchIfBitReset(2), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfMinus, Mode::Relative)),
+    Some((Instruction::AndWithAccumulator, Mode::IndirectY)),
+    Some((Instruction::AndWithAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::BitSet, Mode::ZeroPageX)),
+    Some((Instruction::AndWithAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::RotateLeft, Mode::ZeroPageX)),
+    Some((Instruction::ResetMemoryBit(3), Mode::ZeroPage)),
+    Some((Instruction::SetCarry, Mode::Implied)),
+    Some((Instruction::AndWithAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::Decrement, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::BitSet, Mode::AbsoluteX)),
+    Some((Instruction::AndWithAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::RotateLeft, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitReset(3), Mode::ZeroPageRelative)),
+    Some((Instruction::ReturnFromInterrupt, Mode::Implied)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::XIndirect)),
+    Some((Instruction::NoOperation, Mode::Immediate)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::ZeroPage)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::ZeroPage)),
+    Some((Instruction::LogicalShiftRight, Mode::ZeroPage)),
+    Some((Instruction::ResetMemoryBit(4), Mode::ZeroPage)),
+    Some((Instruction::PushAccumulator, Mode::Implied)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::Immediate)),
+    Some((Instruction::LogicalShiftRight, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::Jump, Mode::Absolute)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::Absolute)),
+    Some((Instruction::LogicalShiftRight, Mode::Absolute)),
+    Some((Instruction::BranchIfBitReset(4), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfOverflowClear, Mode::Relative)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::IndirectY)),
+    Some((
+        Instruction::ExclusiveOrWithAccumulator,
+        Mode::ZeroPageIndirect,
+    )),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::ZeroPageX)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::LogicalShiftRight, Mode::ZeroPageX)),
+    Some((Instruction::ResetMemoryBit(5), Mode::ZeroPage)),
+    Some((Instruction::ClearInterrupt, Mode::Implied)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::PushY, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Absolute)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::LogicalShiftRight, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitReset(5), Mode::ZeroPageRelative)),
+    Some((Instruction::ReturnFromSubroutine, Mode::Implied)),
+    Some((Instruction::AddWithCarry, Mode::XIndirect)),
+    Some((Instruction::NoOperation, Mode::Immediate)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::StoreZero, Mode::ZeroPage)),
+    Some((Instruction::AddWithCarry, Mode::ZeroPage)),
+    Some((Instruction::RotateRight, Mode::ZeroPage)),
+    Some((Instruction::ResetMemoryBit(6), Mode::ZeroPage)),
+    Some((Instruction::PullAccumulator, Mode::Implied)),
+    Some((Instruction::AddWithCarry, Mode::Immediate)),
+    Some((Instruction::RotateRight, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::Jump, Mode::Indirect)),
+    Some((Instruction::AddWithCarry, Mode::Absolute)),
+    Some((Instruction::RotateRight, Mode::Absolute)),
+    Some((Instruction::BranchIfBitReset(6), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfOverflowSet, Mode::Relative)),
+    Some((Instruction::AddWithCarry, Mode::IndirectY)),
+    Some((Instruction::AddWithCarry, Mode::ZeroPageIndirect)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::StoreZero, Mode::ZeroPageX)),
+    Some((Instruction::AddWithCarry, Mode::ZeroPageX)),
+    Some((Instruction::RotateRight, Mode::ZeroPageX)),
+    Some((Instruction::ResetMemoryBit(7), Mode::ZeroPage)),
+    Some((Instruction::SetInterruptDisable, Mode::Implied)),
+    Some((Instruction::AddWithCarry, Mode::AbsoluteY)),
+    Some((Instruction::PullY, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::Jump, Mode::AbsoluteXIndirect)),
+    Some((Instruction::AddWithCarry, Mode::AbsoluteX)),
+    Some((Instruction::RotateRight, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitReset(7), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchAlways, Mode::Relative)),
+    Some((Instruction::StoreAccumulator, Mode::XIndirect)),
+    Some((Instruction::NoOperation, Mode::Immediate)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::StoreY, Mode::ZeroPage)),
+    Some((Instruction::StoreAccumulator, Mode::ZeroPage)),
+    Some((Instruction::StoreX, Mode::ZeroPage)),
+    Some((Instruction::SetMemoryBit(0), Mode::ZeroPage)),
+    Some((Instruction::DecrementY, Mode::Implied)),
+    Some((Instruction::BitSet, Mode::Immediate)),
+    Some((Instruction::TransferXToAccumulator, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::StoreY, Mode::Absolute)),
+    Some((Instruction::StoreAccumulator, Mode::Absolute)),
+    Some((Instruction::StoreX, Mode::Absolute)),
+    Some((Instruction::BranchIfBitSet(0), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfCarryClear, Mode::Relative)),
+    Some((Instruction::StoreAccumulator, Mode::IndirectY)),
+    Some((Instruction::StoreAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::StoreY, Mode::ZeroPageX)),
+    Some((Instruction::StoreAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::StoreX, Mode::ZeroPageY)),
+    Some((Instruction::SetMemoryBit(1), Mode::ZeroPage)),
+    Some((Instruction::TransferYToAccumulator, Mode::Implied)),
+    Some((Instruction::StoreAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::TransferXToStackPointer, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::StoreZero, Mode::Absolute)),
+    Some((Instruction::StoreAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::StoreZero, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitSet(1), Mode::ZeroPageRelative)),
+    Some((Instruction::LoadY, Mode::Immediate)),
+    Some((Instruction::LoadAccumulator, Mode::XIndirect)),
+    Some((Instruction::LoadX, Mode::Immediate)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::LoadY, Mode::ZeroPage)),
+    Some((Instruction::LoadAccumulator, Mode::ZeroPage)),
+    Some((Instruction::LoadX, Mode::ZeroPage)),
+    Some((Instruction::SetMemoryBit(2), Mode::ZeroPage)),
+    Some((Instruction::TransferAccumulatorToY, Mode::Implied)),
+    Some((Instruction::LoadAccumulator, Mode::Immediate)),
+    Some((Instruction::TransferAccumulatorToX, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::LoadY, Mode::Absolute)),
+    Some((Instruction::LoadAccumulator, Mode::Absolute)),
+    Some((Instruction::LoadX, Mode::Absolute)),
+    Some((Instruction::BranchIfBitSet(2), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfCarrySet, Mode::Relative)),
+    Some((Instruction::LoadAccumulator, Mode::IndirectY)),
+    Some((Instruction::LoadAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::LoadY, Mode::ZeroPageX)),
+    Some((Instruction::LoadAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::LoadX, Mode::ZeroPageY)),
+    Some((Instruction::SetMemoryBit(3), Mode::ZeroPage)),
+    Some((Instruction::ClearOverflow, Mode::Implied)),
+    Some((Instruction::LoadAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::TransferStackPointerToX, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::LoadY, Mode::AbsoluteX)),
+    Some((Instruction::LoadAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::LoadX, Mode::AbsoluteY)),
+    Some((Instruction::BranchIfBitSet(3), Mode::ZeroPageRelative)),
+    Some((Instruction::CompareWithY, Mode::Immediate)),
+    Some((Instruction::CompareWithAccumulator, Mode::XIndirect)),
+    Some((Instruction::NoOperation, Mode::Immediate)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::CompareWithY, Mode::ZeroPage)),
+    Some((Instruction::CompareWithAccumulator, Mode::ZeroPage)),
+    Some((Instruction::Decrement, Mode::ZeroPage)),
+    Some((Instruction::SetMemoryBit(4), Mode::ZeroPage)),
+    Some((Instruction::IncrementY, Mode::Implied)),
+    Some((Instruction::CompareWithAccumulator, Mode::Immediate)),
+    Some((Instruction::DecrementX, Mode::Accumulator)),
+    Some((Instruction::WaitForInterrupt, Mode::Implied)),
+    Some((Instruction::CompareWithY, Mode::Absolute)),
+    Some((Instruction::CompareWithAccumulator, Mode::Absolute)),
+    Some((Instruction::Decrement, Mode::Absolute)),
+    Some((Instruction::BranchIfBitSet(4), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfNotEqual, Mode::Relative)),
+    Some((Instruction::CompareWithAccumulator, Mode::IndirectY)),
+    Some((Instruction::CompareWithAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::ZeroPageX)),
+    Some((Instruction::CompareWithAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::Decrement, Mode::ZeroPageX)),
+    Some((Instruction::SetMemoryBit(5), Mode::ZeroPage)),
+    Some((Instruction::ClearDecimal, Mode::Implied)),
+    Some((Instruction::CompareWithAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::PushX, Mode::Implied)),
+    Some((Instruction::Stop, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Absolute)),
+    Some((Instruction::CompareWithAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::Decrement, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitSet(5), Mode::ZeroPageRelative)),
+    Some((Instruction::CompareWithX, Mode::Immediate)),
+    Some((Instruction::SubtractWithCarry, Mode::XIndirect)),
+    Some((Instruction::NoOperation, Mode::Immediate)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::CompareWithX, Mode::ZeroPage)),
+    Some((Instruction::SubtractWithCarry, Mode::ZeroPage)),
+    Some((Instruction::Increment, Mode::ZeroPage)),
+    Some((Instruction::SetMemoryBit(6), Mode::ZeroPage)),
+    Some((Instruction::IncrementX, Mode::Implied)),
+    Some((Instruction::SubtractWithCarry, Mode::Immediate)),
+    Some((Instruction::NoOperation, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::CompareWithX, Mode::Absolute)),
+    Some((Instruction::SubtractWithCarry, Mode::Absolute)),
+    Some((Instruction::Increment, Mode::Absolute)),
+    Some((Instruction::BranchIfBitSet(6), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfEqual, Mode::Relative)),
+    Some((Instruction::SubtractWithCarry, Mode::IndirectY)),
+    Some((Instruction::SubtractWithCarry, Mode::ZeroPageIndirect)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::ZeroPageX)),
+    Some((Instruction::SubtractWithCarry, Mode::ZeroPageX)),
+    Some((Instruction::Increment, Mode::ZeroPageX)),
+    Some((Instruction::SetMemoryBit(7), Mode::ZeroPage)),
+    Some((Instruction::SetDecimal, Mode::Implied)),
+    Some((Instruction::SubtractWithCarry, Mode::AbsoluteY)),
+    Some((Instruction::PullX, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Absolute)),
+    Some((Instruction::SubtractWithCarry, Mode::AbsoluteX)),
+    Some((Instruction::Increment, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitSet(7), Mode::ZeroPageRelative)),
+];
+
+// WDC 65C816. JML shares Jump, BRL shares BranchAlways and WDM is a two
+// byte NoOperation.
+static W65C816_OP_CODES: [OpCode; 256] = [
+    Some((Instruction::Break, Mode::Implied)),
+    Some((Instruction::OrWithAccumulator, Mode::XIndirect)),
+    Some((Instruction::CoProcessor, Mode::Immediate)),
+    Some((Instruction::OrWithAccumulator, Mode::StackRelative)),
+    Some((Instruction::TestAndSetBits, Mode::ZeroPage)),
+    Some((Instruction::OrWithAccumulator, Mode::ZeroPage)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::ZeroPage)),
+    Some((Instruction::OrWithAccumulator, Mode::ZeroPageIndirectLong)),
+    Some((Instruction::PushProcessorStatus, Mode::Implied)),
+    Some((Instruction::OrWithAccumulator, Mode::Immediate)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::Accumulator)),
+    Some((Instruction::PushDirectPage, Mode::Implied)),
+    Some((Instruction::TestAndSetBits, Mode::Absolute)),
+    Some((Instruction::OrWithAccumulator, Mode::Absolute)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::Absolute)),
+    Some((Instruction::OrWithAccumulator, Mode::AbsoluteLong)),
+    Some((Instruction::BranchIfPlus, Mode::Relative)),
+    Some((Instruction::OrWithAccumulator, Mode::IndirectY)),
+    Some((Instruction::OrWithAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::OrWithAccumulator, Mode::StackRelativeIndirectY)),
+    Some((Instruction::TestAndResetBits, Mode::ZeroPage)),
+    Some((Instruction::OrWithAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::ZeroPageX)),
+    Some((Instruction::OrWithAccumulator, Mode::ZeroPageIndirectLongY)),
+    Some((Instruction::ClearCarry, Mode::Implied)),
+    Some((Instruction::OrWithAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::Increment, Mode::Accumulator)),
+    Some((
+        Instruction::TransferAccumulatorToStackPointer,
+        Mode::Implied,
+    )),
+    Some((Instruction::TestAndResetBits, Mode::Absolute)),
+    Some((Instruction::OrWithAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::AbsoluteX)),
+    Some((Instruction::OrWithAccumulator, Mode::AbsoluteLongX)),
+    Some((Instruction::JumpSubroutine, Mode::Absolute)),
+    Some((Instruction::AndWithAccumulator, Mode::XIndirect)),
+    Some((Instruction::JumpSubroutineLong, Mode::AbsoluteLong)),
+    Some((Instruction::AndWithAccumulator, Mode::StackRelative)),
+    Some((Instruction::BitSet, Mode::ZeroPage)),
+    Some((Instruction::AndWithAccumulator, Mode::ZeroPage)),
+    Some((Instruction::RotateLeft, Mode::ZeroPage)),
+    Some((Instruction::AndWithAccumulator, Mode::ZeroPageIndirectLong)),
+    Some((Instruction::PullProcessorStatus, Mode::Implied)),
+    Some((Instruction::AndWithAccumulator, Mode::Immediate)),
+    Some((Instruction::RotateLeft, Mode::Accumulator)),
+    Some((Instruction::PullDirectPage, Mode::Implied)),
+    Some((Instruction::BitSet, Mode::Absolute)),
+    Some((Instruction::AndWithAccumulator, Mode::Absolute)),
+    Some((Instruction::RotateLeft, Mode::Absolute)),
+    Some((Instruction::AndWithAccumulator, Mode::AbsoluteLong)),
+    Some((Instruction::BranchIfMinus, Mode::Relative)),
+    Some((Instruction::AndWithAccumulator, Mode::IndirectY)),
+    Some((Instruction::AndWithAccumulator, Mode::ZeroPageIndirect)),
+    Some((
+        Instruction::AndWithAccumulator,
+        Mode::StackRelativeIndirectY,
+    )),
+    Some((Instruction::BitSet, Mode::ZeroPageX)),
+    Some((Instruction::AndWithAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::RotateLeft, Mode::ZeroPageX)),
+    Some((Instruction::AndWithAccumulator, Mode::ZeroPageIndirectLongY)),
+    Some((Instruction::SetCarry, Mode::Implied)),
+    Some((Instruction::AndWithAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::Decrement, Mode::Accumulator)),
+    Some((
+        Instruction::TransferStackPointerToAccumulator,
+        Mode::Implied,
+    )),
+    Some((Instruction::BitSet, Mode::AbsoluteX)),
+    Some((Instruction::AndWithAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::RotateLeft, Mode::AbsoluteX)),
+    Some((Instruction::AndWithAccumulator, Mode::AbsoluteLongX)),
+    Some((Instruction::ReturnFromInterrupt, Mode::Implied)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::XIndirect)),
+    Some((Instruction::NoOperation, Mode::Immediate)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::StackRelative)),
+    Some((Instruction::BlockMovePositive, Mode::BlockMove)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::ZeroPage)),
+    Some((Instruction::LogicalShiftRight, Mode::ZeroPage)),
+    Some((
+        Instruction::ExclusiveOrWithAccumulator,
+        Mode::ZeroPageIndirectLong,
+    )),
+    Some((Instruction::PushAccumulator, Mode::Implied)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::Immediate)),
+    Some((Instruction::LogicalShiftRight, Mode::Accumulator)),
+    Some((Instruction::PushProgramBank, Mode::Implied)),
+    Some((Instruction::Jump, Mode::Absolute)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::Absolute)),
+    Some((Instruction::LogicalShiftRight, Mode::Absolute)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::AbsoluteLong)),
+    Some((Instruction::BranchIfOverflowClear, Mode::Relative)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::IndirectY)),
+    Some((
+        Instruction::ExclusiveOrWithAccumulator,
+        Mode::ZeroPageIndirect,
+    )),
+    Some((
+        Instruction::ExclusiveOrWithAccumulator,
+        Mode::StackRelativeIndirectY,
+    )),
+    Some((Instruction::BlockMoveNegative, Mode::BlockMove)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::LogicalShiftRight, Mode::ZeroPageX)),
+    Some((
+        Instruction::ExclusiveOrWithAccumulator,
+        Mode::ZeroPageIndirectLongY,
+    )),
+    Some((Instruction::ClearInterrupt, Mode::Implied)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::PushY, Mode::Implied)),
+    Some((Instruction::TransferAccumulatorToDirectPage, Mode::Implied)),
+    Some((Instruction::Jump, Mode::AbsoluteLong)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::LogicalShiftRight, Mode::AbsoluteX)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::AbsoluteLongX)),
+    Some((Instruction::ReturnFromSubroutine, Mode::Implied)),
+    Some((Instruction::AddWithCarry, Mode::XIndirect)),
+    Some((Instruction::PushEffectiveAddress, Mode::RelativeLong)),
+    Some((Instruction::AddWithCarry, Mode::StackRelative)),
+    Some((Instruction::StoreZero, Mode::ZeroPage)),
+    Some((Instruction::AddWithCarry, Mode::ZeroPage)),
+    Some((Instruction::RotateRight, Mode::ZeroPage)),
+    Some((Instruction::AddWithCarry, Mode::ZeroPageIndirectLong)),
+    Some((Instruction::PullAccumulator, Mode::Implied)),
+    Some((Instruction::AddWithCarry, Mode::Immediate)),
+    Some((Instruction::RotateRight, Mode::Accumulator)),
+    Some((Instruction::ReturnFromSubroutineLong, Mode::Implied)),
+    Some((Instruction::Jump, Mode::Indirect)),
+    Some((Instruction::AddWithCarry, Mode::Absolute)),
+    Some((Instruction::RotateRight, Mode::Absolute)),
+    Some((Instruction::AddWithCarry, Mode::AbsoluteLong)),
+    Some((Instruction::BranchIfOverflowSet, Mode::Relative)),
+    Some((Instruction::AddWithCarry, Mode::IndirectY)),
+    Some((Instruction::AddWithCarry, Mode::ZeroPageIndirect)),
+    Some((Instruction::AddWithCarry, Mode::StackRelativeIndirectY)),
+    Some((Instruction::StoreZero, Mode::ZeroPageX)),
+    Some((Instruction::AddWithCarry, Mode::ZeroPageX)),
+    Some((Instruction::RotateRight, Mode::ZeroPageX)),
+    Some((Instruction::AddWithCarry, Mode::ZeroPageIndirectLongY)),
+    Some((Instruction::SetInterruptDisable, Mode::Implied)),
+    Some((Instruction::AddWithCarry, Mode::AbsoluteY)),
+    Some((Instruction::PullY, Mode::Implied)),
+    Some((Instruction::TransferDirectPageToAccumulator, Mode::Implied)),
+    Some((Instruction::Jump, Mode::AbsoluteXIndirect)),
+    Some((Instruction::AddWithCarry, Mode::AbsoluteX)),
+    Some((Instruction::RotateRight, Mode::AbsoluteX)),
+    Some((Instruction::AddWithCarry, Mode::AbsoluteLongX)),
+    Some((Instruction::BranchAlways, Mode::Relative)),
+    Some((Instruction::StoreAccumulator, Mode::XIndirect)),
+    Some((Instruction::BranchAlways, Mode::RelativeLong)),
+    Some((Instruction::StoreAccumulator, Mode::StackRelative)),
+    Some((Instruction::StoreY, Mode::ZeroPage)),
+    Some((Instruction::StoreAccumulator, Mode::ZeroPage)),
+    Some((Instruction::StoreX, Mode::ZeroPage)),
+    Some((Instruction::StoreAccumulator, Mode::ZeroPageIndirectLong)),
+    Some((Instruction::DecrementY, Mode::Implied)),
+    Some((Instruction::BitSet, Mode::Immediate)),
+    Some((Instruction::TransferXToAccumulator, Mode::Implied)),
+    Some((Instruction::PushDataBank, Mode::Implied)),
+    Some((Instruction::StoreY, Mode::Absolute)),
+    Some((Instruction::StoreAccumulator, Mode::Absolute)),
+    Some((Instruction::StoreX, Mode::Absolute)),
+    Some((Instruction::StoreAccumulator, Mode::AbsoluteLong)),
+    Some((Instruction::BranchIfCarryClear, Mode::Relative)),
+    Some((Instruction::StoreAccumulator, Mode::IndirectY)),
+    Some((Instruction::StoreAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::StoreAccumulator, Mode::StackRelativeIndirectY)),
+    Some((Instruction::StoreY, Mode::ZeroPageX)),
+    Some((Instruction::StoreAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::StoreX, Mode::ZeroPageY)),
+    Some((Instruction::StoreAccumulator, Mode::ZeroPageIndirectLongY)),
+    Some((Instruction::TransferYToAccumulator, Mode::Implied)),
+    Some((Instruction::StoreAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::TransferXToStackPointer, Mode::Implied)),
+    Some((Instruction::TransferXToY, Mode::Implied)),
+    Some((Instruction::StoreZero, Mode::Absolute)),
+    Some((Instruction::StoreAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::StoreZero, Mode::AbsoluteX)),
+    Some((Instruction::StoreAccumulator, Mode::AbsoluteLongX)),
+    Some((Instruction::LoadY, Mode::Immediate)),
+    Some((Instruction::LoadAccumulator, Mode::XIndirect)),
+    Some((Instruction::LoadX, Mode::Immediate)),
+    Some((Instruction::LoadAccumulator, Mode::StackRelative)),
+    Some((Instruction::LoadY, Mode::ZeroPage)),
+    Some((Instruction::LoadAccumulator, Mode::ZeroPage)),
+    Some((Instruction::LoadX, Mode::ZeroPage)),
+    Some((Instruction::LoadAccumulator, Mode::ZeroPageIndirectLong)),
+    Some((Instruction::TransferAccumulatorToY, Mode::Implied)),
+    Some((Instruction::LoadAccumulator, Mode::Immediate)),
+    Some((Instruction::TransferAccumulatorToX, Mode::Implied)),
+    Some((Instruction::PullDataBank, Mode::Implied)),
+    Some((Instruction::LoadY, Mode::Absolute)),
+    Some((Instruction::LoadAccumulator, Mode::Absolute)),
+    Some((Instruction::LoadX, Mode::Absolute)),
+    Some((Instruction::LoadAccumulator, Mode::AbsoluteLong)),
+    Some((Instruction::BranchIfCarrySet, Mode::Relative)),
+    Some((Instruction::LoadAccumulator, Mode::IndirectY)),
+    Some((Instruction::LoadAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::LoadAccumulator, Mode::StackRelativeIndirectY)),
+    Some((Instruction::LoadY, Mode::ZeroPageX)),
+    Some((Instruction::LoadAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::LoadX, Mode::ZeroPageY)),
+    Some((Instruction::LoadAccumulator, Mode::ZeroPageIndirectLongY)),
+    Some((Instruction::ClearOverflow, Mode::Implied)),
+    Some((Instruction::LoadAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::TransferStackPointerToX, Mode::Implied)),
+    Some((Instruction::TransferYToX, Mode::Implied)),
+    Some((Instruction::LoadY, Mode::AbsoluteX)),
+    Some((Instruction::LoadAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::LoadX, Mode::AbsoluteY)),
+    Some((Instruction::LoadAccumulator, Mode::AbsoluteLongX)),
+    Some((Instruction::CompareWithY, Mode::Immediate)),
+    Some((Instruction::CompareWithAccumulator, Mode::XIndirect)),
+    Some((Instruction::ResetStatusBits, Mode::Immediate)),
+    Some((Instruction::CompareWithAccumulator, Mode::StackRelative)),
+    Some((Instruction::CompareWithY, Mode::ZeroPage)),
+    Some((Instruction::CompareWithAccumulator, Mode::ZeroPage)),
+    Some((Instruction::Decrement, Mode::ZeroPage)),
+    Some((
+        Instruction::CompareWithAccumulator,
+        Mode::ZeroPageIndirectLong,
+    )),
+    Some((Instruction::IncrementY, Mode::Implied)),
+    Some((Instruction::CompareWithAccumulator, Mode::Immediate)),
+    Some((Instruction::DecrementX, Mode::Implied)),
+    Some((Instruction::WaitForInterrupt, Mode::Implied)),
+    Some((Instruction::CompareWithY, Mode::Absolute)),
+    Some((Instruction::CompareWithAccumulator, Mode::Absolute)),
+    Some((Instruction::Decrement, Mode::Absolute)),
+    Some((Instruction::CompareWithAccumulator, Mode::AbsoluteLong)),
+    Some((Instruction::BranchIfNotEqual, Mode::Relative)),
+    Some((Instruction::CompareWithAccumulator, Mode::IndirectY)),
+    Some((Instruction::CompareWithAccumulator, Mode::ZeroPageIndirect)),
+    Some((
+        Instruction::CompareWithAccumulator,
+        Mode::StackRelativeIndirectY,
+    )),
+    Some((Instruction::PushEffectiveAddress, Mode::ZeroPageIndirect)),
+    Some((Instruction::CompareWithAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::Decrement, Mode::ZeroPageX)),
+    Some((
+        Instruction::CompareWithAccumulator,
+        Mode::ZeroPageIndirectLongY,
+    )),
+    Some((Instruction::ClearDecimal, Mode::Implied)),
+    Some((Instruction::CompareWithAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::PushX, Mode::Implied)),
+    Some((Instruction::Stop, Mode::Implied)),
+    Some((Instruction::Jump, Mode::AbsoluteIndirectLong)),
+    Some((Instruction::CompareWithAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::Decrement, Mode::AbsoluteX)),
+    Some((Instruction::CompareWithAccumulator, Mode::AbsoluteLongX)),
+    Some((Instruction::CompareWithX, Mode::Immediate)),
+    Some((Instruction::SubtractWithCarry, Mode::XIndirect)),
+    Some((Instruction::SetStatusBits, Mode::Immediate)),
+    Some((Instruction::SubtractWithCarry, Mode::StackRelative)),
+    Some((Instruction::CompareWithX, Mode::ZeroPage)),
+    Some((Instruction::SubtractWithCarry, Mode::ZeroPage)),
+    Some((Instruction::Increment, Mode::ZeroPage)),
+    Some((Instruction::SubtractWithCarry, Mode::ZeroPageIndirectLong)),
+    Some((Instruction::IncrementX, Mode::Implied)),
+    Some((Instruction::SubtractWithCarry, Mode::Immediate)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::ExchangeBAndA, Mode::Implied)),
+    Some((Instruction::CompareWithX, Mode::Absolute)),
+    Some((Instruction::SubtractWithCarry, Mode::Absolute)),
+    Some((Instruction::Increment, Mode::Absolute)),
+    Some((Instruction::SubtractWithCarry, Mode::AbsoluteLong)),
+    Some((Instruction::BranchIfEqual, Mode::Relative)),
+    Some((Instruction::SubtractWithCarry, Mode::IndirectY)),
+    Some((Instruction::SubtractWithCarry, Mode::ZeroPageIndirect)),
+    Some((Instruction::SubtractWithCarry, Mode::StackRelativeIndirectY)),
+    Some((Instruction::PushEffectiveAddress, Mode::Absolute)),
+    Some((Instruction::SubtractWithCarry, Mode::ZeroPageX)),
+    Some((Instruction::Increment, Mode::ZeroPageX)),
+    Some((Instruction::SubtractWithCarry, Mode::ZeroPageIndirectLongY)),
+    Some((Instruction::SetDecimal, Mode::Implied)),
+    Some((Instruction::SubtractWithCarry, Mode::AbsoluteY)),
+    Some((Instruction::PullX, Mode::Implied)),
+    Some((Instruction::ExchangeCarryAndEmulation, Mode::Implied)),
+    Some((Instruction::JumpSubroutine, Mode::AbsoluteXIndirect)),
+    Some((Instruction::SubtractWithCarry, Mode::AbsoluteX)),
+    Some((Instruction::Increment, Mode::AbsoluteX)),
+    Some((Instruction::SubtractWithCarry, Mode::AbsoluteLongX)),
+];
+
+// Hudson HuC6280: the 65C02 with Rockwell bit instructions, without
+// WAI and STP, plus the block transfer, MMU and VDC instructions.
+static HUC6280_OP_CODES: [OpCode; 256] = [
+    Some((Instruction::Break, Mode::Implied)),
+    Some((Instruction::OrWithAccumulator, Mode::XIndirect)),
+    Some((Instruction::SwapXAndY, Mode::Implied)),
+    Some((Instruction::StoreVdc(0), Mode::Immediate)),
+    Some((Instruction::TestAndSetBits, Mode::ZeroPage)),
+    Some((Instruction::OrWithAccumulator, Mode::ZeroPage)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::ZeroPage)),
+    Some((Instruction::ResetMemoryBit(0), Mode::ZeroPage)),
+    Some((Instruction::PushProcessorStatus, Mode::Implied)),
+    Some((Instruction::OrWithAccumulator, Mode::Immediate)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::TestAndSetBits, Mode::Absolute)),
+    Some((Instruction::OrWithAccumulator, Mode::Absolute)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::Absolute)),
+    Some((Instruction::BranchIfBitReset(0), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfPlus, Mode::Relative)),
+    Some((Instruction::OrWithAccumulator, Mode::IndirectY)),
+    Some((Instruction::OrWithAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::StoreVdc(1), Mode::Immediate)),
+    Some((Instruction::TestAndResetBits, Mode::ZeroPage)),
+    Some((Instruction::OrWithAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::ZeroPageX)),
+    Some((Instruction::ResetMemoryBit(1), Mode::ZeroPage)),
+    Some((Instruction::ClearCarry, Mode::Implied)),
+    Some((Instruction::OrWithAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::Increment, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::TestAndResetBits, Mode::Absolute)),
+    Some((Instruction::OrWithAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::ArithmeticShiftLeft, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitReset(1), Mode::ZeroPageRelative)),
+    Some((Instruction::JumpSubroutine, Mode::Absolute)),
+    Some((Instruction::AndWithAccumulator, Mode::XIndirect)),
+    Some((Instruction::SwapAccumulatorAndX, Mode::Implied)),
+    Some((Instruction::StoreVdc(2), Mode::Immediate)),
+    Some((Instruction::BitSet, Mode::ZeroPage)),
+    Some((Instruction::AndWithAccumulator, Mode::ZeroPage)),
+    Some((Instruction::RotateLeft, Mode::ZeroPage)),
+    Some((Instruction::ResetMemoryBit(2), Mode::ZeroPage)),
+    Some((Instruction::PullProcessorStatus, Mode::Implied)),
+    Some((Instruction::AndWithAccumulator, Mode::Immediate)),
+    Some((Instruction::RotateLeft, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::BitSet, Mode::Absolute)),
+    Some((Instruction::AndWithAccumulator, Mode::Absolute)),
+    Some((Instruction::RotateLeft, Mode::Absolute)),
+    Some((Instruction::BranchIfBitReset(2), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfMinus, Mode::Relative)),
+    Some((Instruction::AndWithAccumulator, Mode::IndirectY)),
+    Some((Instruction::AndWithAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::BitSet, Mode::ZeroPageX)),
+    Some((Instruction::AndWithAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::RotateLeft, Mode::ZeroPageX)),
+    Some((Instruction::ResetMemoryBit(3), Mode::ZeroPage)),
+    Some((Instruction::SetCarry, Mode::Implied)),
+    Some((Instruction::AndWithAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::Decrement, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::BitSet, Mode::AbsoluteX)),
+    Some((Instruction::AndWithAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::RotateLeft, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitReset(3), Mode::ZeroPageRelative)),
+    Some((Instruction::ReturnFromInterrupt, Mode::Implied)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::XIndirect)),
+    Some((Instruction::SwapAccumulatorAndY, Mode::Implied)),
+    Some((Instruction::TransferMprToAccumulator, Mode::Immediate)),
+    Some((Instruction::BranchSubroutine, Mode::Relative)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::ZeroPage)),
+    Some((Instruction::LogicalShiftRight, Mode::ZeroPage)),
+    Some((Instruction::ResetMemoryBit(4), Mode::ZeroPage)),
+    Some((Instruction::PushAccumulator, Mode::Implied)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::Immediate)),
+    Some((Instruction::LogicalShiftRight, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::Jump, Mode::Absolute)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::Absolute)),
+    Some((Instruction::LogicalShiftRight, Mode::Absolute)),
+    Some((Instruction::BranchIfBitReset(4), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfOverflowClear, Mode::Relative)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::IndirectY)),
+    Some((
+        Instruction::ExclusiveOrWithAccumulator,
+        Mode::ZeroPageIndirect,
+    )),
+    Some((Instruction::TransferAccumulatorToMpr, Mode::Immediate)),
+    Some((Instruction::ChangeSpeedLow, Mode::Implied)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::LogicalShiftRight, Mode::ZeroPageX)),
+    Some((Instruction::ResetMemoryBit(5), Mode::ZeroPage)),
+    Some((Instruction::ClearInterrupt, Mode::Implied)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::PushY, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::ExclusiveOrWithAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::LogicalShiftRight, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitReset(5), Mode::ZeroPageRelative)),
+    Some((Instruction::ReturnFromSubroutine, Mode::Implied)),
+    Some((Instruction::AddWithCarry, Mode::XIndirect)),
+    Some((Instruction::ClearAccumulator, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::StoreZero, Mode::ZeroPage)),
+    Some((Instruction::AddWithCarry, Mode::ZeroPage)),
+    Some((Instruction::RotateRight, Mode::ZeroPage)),
+    Some((Instruction::ResetMemoryBit(6), Mode::ZeroPage)),
+    Some((Instruction::PullAccumulator, Mode::Implied)),
+    Some((Instruction::AddWithCarry, Mode::Immediate)),
+    Some((Instruction::RotateRight, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::Jump, Mode::Indirect)),
+    Some((Instruction::AddWithCarry, Mode::Absolute)),
+    Some((Instruction::RotateRight, Mode::Absolute)),
+    Some((Instruction::BranchIfBitReset(6), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfOverflowSet, Mode::Relative)),
+    Some((Instruction::AddWithCarry, Mode::IndirectY)),
+    Some((Instruction::AddWithCarry, Mode::ZeroPageIndirect)),
+    Some((Instruction::TransferIncrementIncrement, Mode::BlockTransfer)),
+    Some((Instruction::StoreZero, Mode::ZeroPageX)),
+    Some((Instruction::AddWithCarry, Mode::ZeroPageX)),
+    Some((Instruction::RotateRight, Mode::ZeroPageX)),
+    Some((Instruction::ResetMemoryBit(7), Mode::ZeroPage)),
+    Some((Instruction::SetInterruptDisable, Mode::Implied)),
+    Some((Instruction::AddWithCarry, Mode::AbsoluteY)),
+    Some((Instruction::PullY, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::Jump, Mode::AbsoluteXIndirect)),
+    Some((Instruction::AddWithCarry, Mode::AbsoluteX)),
+    Some((Instruction::RotateRight, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitReset(7), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchAlways, Mode::Relative)),
+    Some((Instruction::StoreAccumulator, Mode::XIndirect)),
+    Some((Instruction::ClearX, Mode::Implied)),
+    Some((Instruction::TestMemory, Mode::ImmediateZeroPage)),
+    Some((Instruction::StoreY, Mode::ZeroPage)),
+    Some((Instruction::StoreAccumulator, Mode::ZeroPage)),
+    Some((Instruction::StoreX, Mode::ZeroPage)),
+    Some((Instruction::SetMemoryBit(0), Mode::ZeroPage)),
+    Some((Instruction::DecrementY, Mode::Implied)),
+    Some((Instruction::BitSet, Mode::Immediate)),
+    Some((Instruction::TransferXToAccumulator, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::StoreY, Mode::Absolute)),
+    Some((Instruction::StoreAccumulator, Mode::Absolute)),
+    Some((Instruction::StoreX, Mode::Absolute)),
+    Some((Instruction::BranchIfBitSet(0), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfCarryClear, Mode::Relative)),
+    Some((Instruction::StoreAccumulator, Mode::IndirectY)),
+    Some((Instruction::StoreAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::TestMemory, Mode::ImmediateAbsolute)),
+    Some((Instruction::StoreY, Mode::ZeroPageX)),
+    Some((Instruction::StoreAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::StoreX, Mode::ZeroPageY)),
+    Some((Instruction::SetMemoryBit(1), Mode::ZeroPage)),
+    Some((Instruction::TransferYToAccumulator, Mode::Implied)),
+    Some((Instruction::StoreAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::TransferXToStackPointer, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::StoreZero, Mode::Absolute)),
+    Some((Instruction::StoreAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::StoreZero, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitSet(1), Mode::ZeroPageRelative)),
+    Some((Instruction::LoadY, Mode::Immediate)),
+    Some((Instruction::LoadAccumulator, Mode::XIndirect)),
+    Some((Instruction::LoadX, Mode::Immediate)),
+    Some((Instruction::TestMemory, Mode::ImmediateZeroPageX)),
+    Some((Instruction::LoadY, Mode::ZeroPage)),
+    Some((Instruction::LoadAccumulator, Mode::ZeroPage)),
+    Some((Instruction::LoadX, Mode::ZeroPage)),
+    Some((Instruction::SetMemoryBit(2), Mode::ZeroPage)),
+    Some((Instruction::TransferAccumulatorToY, Mode::Implied)),
+    Some((Instruction::LoadAccumulator, Mode::Immediate)),
+    Some((Instruction::TransferAccumulatorToX, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::LoadY, Mode::Absolute)),
+    Some((Instruction::LoadAccumulator, Mode::Absolute)),
+    Some((Instruction::LoadX, Mode::Absolute)),
+    Some((Instruction::BranchIfBitSet(2), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfCarrySet, Mode::Relative)),
+    Some((Instruction::LoadAccumulator, Mode::IndirectY)),
+    Some((Instruction::LoadAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::TestMemory, Mode::ImmediateAbsoluteX)),
+    Some((Instruction::LoadY, Mode::ZeroPageX)),
+    Some((Instruction::LoadAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::LoadX, Mode::ZeroPageY)),
+    Some((Instruction::SetMemoryBit(3), Mode::ZeroPage)),
+    Some((Instruction::ClearOverflow, Mode::Implied)),
+    Some((Instruction::LoadAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::TransferStackPointerToX, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::LoadY, Mode::AbsoluteX)),
+    Some((Instruction::LoadAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::LoadX, Mode::AbsoluteY)),
+    Some((Instruction::BranchIfBitSet(3), Mode::ZeroPageRelative)),
+    Some((Instruction::CompareWithY, Mode::Immediate)),
+    Some((Instruction::CompareWithAccumulator, Mode::XIndirect)),
+    Some((Instruction::ClearY, Mode::Implied)),
+    Some((Instruction::TransferDecrementDecrement, Mode::BlockTransfer)),
+    Some((Instruction::CompareWithY, Mode::ZeroPage)),
+    Some((Instruction::CompareWithAccumulator, Mode::ZeroPage)),
+    Some((Instruction::Decrement, Mode::ZeroPage)),
+    Some((Instruction::SetMemoryBit(4), Mode::ZeroPage)),
+    Some((Instruction::IncrementY, Mode::Implied)),
+    Some((Instruction::CompareWithAccumulator, Mode::Immediate)),
+    Some((Instruction::DecrementX, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::CompareWithY, Mode::Absolute)),
+    Some((Instruction::CompareWithAccumulator, Mode::Absolute)),
+    Some((Instruction::Decrement, Mode::Absolute)),
+    Some((Instruction::BranchIfBitSet(4), Mode::ZeroPageRelative)),
+    Some((Instruction::BranchIfNotEqual, Mode::Relative)),
+    Some((Instruction::CompareWithAccumulator, Mode::IndirectY)),
+    Some((Instruction::CompareWithAccumulator, Mode::ZeroPageIndirect)),
+    Some((Instruction::TransferIncrementNone, Mode::BlockTransfer)),
+    Some((Instruction::ChangeSpeedHigh, Mode::Implied)),
+    Some((Instruction::CompareWithAccumulator, Mode::ZeroPageX)),
+    Some((Instruction::Decrement, Mode::ZeroPageX)),
+    Some((Instruction::SetMemoryBit(5), Mode::ZeroPage)),
+    Some((Instruction::ClearDecimal, Mode::Implied)),
+    Some((Instruction::CompareWithAccumulator, Mode::AbsoluteY)),
+    Some((Instruction::PushX, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::CompareWithAccumulator, Mode::AbsoluteX)),
+    Some((Instruction::Decrement, Mode::AbsoluteX)),
+    Some((Instruction::BranchIfBitSet(5), Mode::ZeroPageRelative)),
+    Some((Instruction::CompareWithX, Mode::Immediate)),
+    Some((Instruction::SubtractWithCarry, Mode::XIndirect)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::TransferIncrementAlternate, Mode::BlockTransfer)),
+    Some((Instruction::CompareWithX, Mode::ZeroPage)),
+    Some((Instruction::SubtractWithCarry, Mode::ZeroPage)),
+    Some((Instruction::Increment, Mode::ZeroPage)),
+    Some((Instruction::SetMemoryBit(6), Mode::ZeroPage)),
+    Some((Instruction::IncrementX, Mode::Implied)),
+    Some((Instruction::SubtractWithCarry, Mode::Immediate)),
+    Some((Instruction::NoOperation, Mode::Accumulator)),
+    Some((Instruction::NoOperation, Mode::Implied)),
     Some((Instruction::CompareWithX, Mode::Absolute)),
     Some((Instruction::SubtractWithCarry, Mode::Absolute)),
     Some((Instruction::Increment, Mode::Absolute)),
-    None,
+    Some((Instruction::BranchIfBitSet(6), Mode::ZeroPageRelative)),
     Some((Instruction::BranchIfEqual, Mode::Relative)),
     Some((Instruction::SubtractWithCarry, Mode::IndirectY)),
-    None,
-    None,
-    None,
+    Some((Instruction::SubtractWithCarry, Mode::ZeroPageIndirect)),
+    Some((Instruction::TransferAlternateIncrement, Mode::BlockTransfer)),
+    Some((Instruction::SetMemoryOperation, Mode::Implied)),
     Some((Instruction::SubtractWithCarry, Mode::ZeroPageX)),
     Some((Instruction::Increment, Mode::ZeroPageX)),
-    None,
+    Some((Instruction::SetMemoryBit(7), Mode::ZeroPage)),
     Some((Instruction::SetDecimal, Mode::Implied)),
     Some((Instruction::SubtractWithCarry, Mode::AbsoluteY)),
-    None,
-    None,
-    None,
+    Some((Instruction::PullX, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Implied)),
+    Some((Instruction::NoOperation, Mode::Implied)),
     Some((Instruction::SubtractWithCarry, Mode::AbsoluteX)),
     Some((Instruction::Increment, Mode::AbsoluteX)),
-    None,
+    Some((Instruction::BranchIfBitSet(7), Mode::ZeroPageRelative)),
+];
+
+// Datasheet cycle counts, without the branch-taken and page-crossing
+// penalties.
+static CYCLES: [u8; 256] = [
+    7, 6, 2, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6, // 0x00
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x10
+    6, 6, 2, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6, // 0x20
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x30
+    6, 6, 2, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6, // 0x40
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x50
+    6, 6, 2, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6, // 0x60
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0x70
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // 0x80
+    2, 6, 2, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5, // 0x90
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // 0xa0
+    2, 5, 2, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4, // 0xb0
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // 0xc0
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0xd0
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // 0xe0
+    2, 5, 2, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 0xf0
+];
+
+static CMOS_CYCLES: [u8; 256] = [
+    7, 6, 2, 1, 5, 3, 5, 5, 3, 2, 2, 1, 6, 4, 6, 5, // 0x00
+    2, 5, 5, 1, 5, 4, 6, 5, 2, 4, 2, 1, 6, 4, 6, 5, // 0x10
+    6, 6, 2, 1, 3, 3, 5, 5, 4, 2, 2, 1, 4, 4, 6, 5, // 0x20
+    2, 5, 5, 1, 4, 4, 6, 5, 2, 4, 2, 1, 4, 4, 6, 5, // 0x30
+    6, 6, 2, 1, 3, 3, 5, 5, 3, 2, 2, 1, 3, 4, 6, 5, // 0x40
+    2, 5, 5, 1, 4, 4, 6, 5, 2, 4, 3, 1, 8, 4, 6, 5, // 0x50
+    6, 6, 2, 1, 3, 3, 5, 5, 4, 2, 2, 1, 6, 4, 6, 5, // 0x60
+    2, 5, 5, 1, 4, 4, 6, 5, 2, 4, 4, 1, 6, 4, 6, 5, // 0x70
+    3, 6, 2, 1, 3, 3, 3, 5, 2, 2, 2, 1, 4, 4, 4, 5, // 0x80
+    2, 6, 5, 1, 4, 4, 4, 5, 2, 5, 2, 1, 4, 5, 5, 5, // 0x90
+    2, 6, 2, 1, 3, 3, 3, 5, 2, 2, 2, 1, 4, 4, 4, 5, // 0xa0
+    2, 5, 5, 1, 4, 4, 4, 5, 2, 4, 2, 1, 4, 4, 4, 5, // 0xb0
+    2, 6, 2, 1, 3, 3, 5, 5, 2, 2, 2, 3, 4, 4, 6, 5, // 0xc0
+    2, 5, 5, 1, 4, 4, 6, 5, 2, 4, 3, 3, 4, 4, 7, 5, // 0xd0
+    2, 6, 2, 1, 3, 3, 5, 5, 2, 2, 2, 1, 4, 4, 6, 5, // 0xe0
+    2, 5, 5, 1, 4, 4, 6, 5, 2, 4, 4, 1, 4, 4, 7, 5, // 0xf0
+];
+
+// Block transfers add 6 cycles per byte, taken branches 2 and the T flag
+// 3 on top of these.
+static HUC6280_CYCLES: [u8; 256] = [
+    8, 7, 3, 4, 6, 4, 6, 7, 3, 2, 2, 2, 7, 5, 7, 6, // 0x00
+    2, 7, 7, 4, 6, 4, 6, 7, 2, 5, 2, 2, 7, 5, 7, 6, // 0x10
+    7, 7, 3, 4, 4, 4, 6, 7, 4, 2, 2, 2, 5, 5, 7, 6, // 0x20
+    2, 7, 7, 2, 4, 4, 6, 7, 2, 5, 2, 2, 5, 5, 7, 6, // 0x30
+    7, 7, 3, 4, 8, 4, 6, 7, 3, 2, 2, 2, 4, 5, 7, 6, // 0x40
+    2, 7, 7, 5, 3, 4, 6, 7, 2, 5, 3, 2, 2, 5, 7, 6, // 0x50
+    7, 7, 2, 2, 4, 4, 6, 7, 4, 2, 2, 2, 7, 5, 7, 6, // 0x60
+    2, 7, 7, 17, 4, 4, 6, 7, 2, 5, 4, 2, 7, 5, 7, 6, // 0x70
+    4, 7, 2, 7, 4, 4, 4, 7, 2, 2, 2, 2, 5, 5, 5, 6, // 0x80
+    2, 7, 7, 8, 4, 4, 4, 7, 2, 5, 2, 2, 5, 5, 5, 6, // 0x90
+    2, 7, 2, 7, 4, 4, 4, 7, 2, 2, 2, 2, 5, 5, 5, 6, // 0xa0
+    2, 7, 7, 8, 4, 4, 4, 7, 2, 5, 2, 2, 5, 5, 5, 6, // 0xb0
+    2, 7, 2, 17, 4, 4, 6, 7, 2, 2, 2, 2, 5, 5, 7, 6, // 0xc0
+    2, 7, 7, 17, 3, 4, 6, 7, 2, 5, 3, 2, 2, 5, 7, 6, // 0xd0
+    2, 7, 2, 17, 4, 4, 6, 7, 2, 2, 2, 2, 5, 5, 7, 6, // 0xe0
+    2, 7, 7, 17, 2, 4, 6, 7, 2, 5, 4, 2, 2, 5, 7, 6, // 0xf0
 ];
 
 pub fn get(opcode: u8) -> OpCode {
     OP_CODES[opcode as usize]
 }
+
+pub fn get_cmos(opcode: u8) -> OpCode {
+    CMOS_OP_CODES[opcode as usize]
+}
+
+pub fn get_w65c816(opcode: u8) -> OpCode {
+    W65C816_OP_CODES[opcode as usize]
+}
+
+pub fn get_huc6280(opcode: u8) -> OpCode {
+    HUC6280_OP_CODES[opcode as usize]
+}
+
+pub fn decode(variant: Variant, opcode: u8) -> OpCode {
+    if variant.is_cmos() {
+        get_cmos(opcode)
+    } else {
+        get(opcode)
+    }
+}
+
+// Undocumented NMOS instructions, including the SBC duplicate at $EB,
+// and the NOPs other than $EA.
+pub fn is_illegal(variant: Variant, opcode: u8) -> bool {
+    match decode(variant, opcode) {
+        Some((Instruction::NoOperation, _)) => opcode != 0xea,
+        Some(_) if opcode == 0xeb => !variant.is_cmos(),
+        Some((instruction, _)) => matches!(
+            instruction,
+            Instruction::AndAndRotateRight
+                | Instruction::AndAndShiftRight
+                | Instruction::AndWithCarry
+                | Instruction::AndXAndImmediate
+                | Instruction::AndXAndSubtract
+                | Instruction::DecrementAndCompare
+                | Instruction::IncrementAndSubtract
+                | Instruction::Jam
+                | Instruction::LoadAccumulatorAndX
+                | Instruction::LoadAccumulatorAndXImmediate
+                | Instruction::LoadStackPointerAnd
+                | Instruction::RotateLeftAndAnd
+                | Instruction::RotateRightAndAdd
+                | Instruction::ShiftLeftAndOr
+                | Instruction::ShiftRightAndExclusiveOr
+                | Instruction::StoreAccumulatorAndX
+                | Instruction::StoreAccumulatorAndXAndHigh
+                | Instruction::StoreXAndHigh
+                | Instruction::StoreYAndHigh
+                | Instruction::TransferAndXToStackPointer
+        ),
+        None => true,
+    }
+}
+
+pub fn cycles(opcode: u8) -> u8 {
+    CYCLES[opcode as usize]
+}
+
+// Only reads through an indexed mode pay for a page crossing; stores and
+// read-modify-write instructions always spend the fix-up cycle.
+pub fn page_crossing_penalty(opcode: u8) -> bool {
+    match get(opcode) {
+        Some((instruction, Mode::AbsoluteX | Mode::AbsoluteY | Mode::IndirectY)) => !matches!(
+            instruction,
+            Instruction::StoreAccumulator
+                | Instruction::ArithmeticShiftLeft
+                | Instruction::Decrement
+                | Instruction::Increment
+                | Instruction::LogicalShiftRight
+                | Instruction::RotateLeft
+                | Instruction::RotateRight
+                | Instruction::DecrementAndCompare
+                | Instruction::IncrementAndSubtract
+                | Instruction::RotateLeftAndAnd
+                | Instruction::RotateRightAndAdd
+                | Instruction::ShiftLeftAndOr
+                | Instruction::ShiftRightAndExclusiveOr
+                | Instruction::StoreAccumulatorAndXAndHigh
+                | Instruction::StoreXAndHigh
+                | Instruction::StoreYAndHigh
+                | Instruction::TransferAndXToStackPointer
+        ),
+        _ => false,
+    }
+}
+
+pub fn cycles_cmos(opcode: u8) -> u8 {
+    CMOS_CYCLES[opcode as usize]
+}
+
+// The 65C02 also lets shifts and rotates through abs,X skip the fix-up
+// cycle when no page is crossed.
+pub fn page_crossing_penalty_cmos(opcode: u8) -> bool {
+    match get_cmos(opcode) {
+        Some((instruction, Mode::AbsoluteX | Mode::AbsoluteY | Mode::IndirectY)) => !matches!(
+            instruction,
+            Instruction::StoreAccumulator
+                | Instruction::StoreZero
+                | Instruction::Decrement
+                | Instruction::Increment
+        ),
+        _ => false,
+    }
+}
+
+pub fn cycles_huc6280(opcode: u8) -> u8 {
+    HUC6280_CYCLES[opcode as usize]
+}
@@ -0,0 +1,7 @@
+// Who pushes the status register: PHP and BRK push it with B set, IRQ and
+// NMI with B clear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushSource {
+    Instruction,
+    Interrupt,
+}
@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Registers {
     pub program_counter: u16,
     pub stack_pointer: u8,
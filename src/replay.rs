@@ -0,0 +1,64 @@
+use crate::registers::Registers;
+use crate::state::State;
+use crate::status_flags::StatusFlags;
+
+pub struct Snapshot {
+    pub registers: Registers,
+    pub status_flags: StatusFlags,
+    pub state: State,
+}
+
+pub enum Access {
+    Replayed(u8),
+    Performed,
+    Skipped,
+}
+
+pub struct Replay {
+    pub snapshot: Snapshot,
+    pub interrupt: Option<u16>,
+    log: Vec<u8>,
+    index: usize,
+    limit: usize,
+}
+
+impl Replay {
+    pub fn new(snapshot: Snapshot, interrupt: Option<u16>) -> Self {
+        Self {
+            snapshot,
+            interrupt,
+            log: Vec::new(),
+            index: 0,
+            limit: 0,
+        }
+    }
+
+    pub fn rewind(&mut self) {
+        self.index = 0;
+        self.limit = self.log.len();
+    }
+
+    pub fn advance(&mut self) -> Access {
+        let index = self.index;
+        self.index += 1;
+        if index < self.log.len() {
+            Access::Replayed(self.log[index])
+        } else if index == self.limit {
+            Access::Performed
+        } else {
+            Access::Skipped
+        }
+    }
+
+    pub fn record(&mut self, value: u8) {
+        self.log.push(value);
+    }
+
+    pub fn is_first_cycle(&self) -> bool {
+        self.limit == 0
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.index <= self.log.len()
+    }
+}
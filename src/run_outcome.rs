@@ -0,0 +1,7 @@
+// How run_cycles ended: Finished holds the cycles the last instruction ran
+// past the end of the slice, Stopped the cycles run before a hook stopped it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    Finished(u64),
+    Stopped(u64),
+}
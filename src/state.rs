@@ -0,0 +1,8 @@
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum State {
+    #[default]
+    Running,
+    Halted(u16),
+    Waiting,
+    Stopped(u16),
+}
@@ -1,11 +1,14 @@
-#[derive(Debug)]
+use crate::push_source::PushSource;
+
+// B and bit 5 are not flags: they only exist in the byte pushed to the
+// stack, where bit 5 always reads as 1 and B tells a BRK or PHP from an
+// interrupt.
+#[derive(Debug, Clone, Copy)]
 pub struct StatusFlags {
     pub carry: bool,
     pub zero: bool,
     pub interrupt: bool,
     pub decimal: bool,
-    pub brk: bool,
-    pub ignored: bool,
     pub overflow: bool,
     pub negative: bool,
 }
@@ -23,13 +26,22 @@ impl StatusFlags {
             zero: false,
             interrupt: false,
             decimal: false,
-            brk: false,
-            ignored: false,
             overflow: false,
             negative: false,
         }
     }
 
+    pub fn from_byte(byte: u8) -> Self {
+        Self {
+            carry: byte & 0x01 != 0,
+            zero: byte & 0x02 != 0,
+            interrupt: byte & 0x04 != 0,
+            decimal: byte & 0x08 != 0,
+            overflow: byte & 0x40 != 0,
+            negative: byte & 0x80 != 0,
+        }
+    }
+
     pub fn to_byte(&self) -> u8 {
         let mut byte = 0x00;
         if self.carry {
@@ -44,12 +56,6 @@ impl StatusFlags {
         if self.decimal {
             byte |= 0x08;
         }
-        if self.brk {
-            byte |= 0x10;
-        }
-        if self.ignored {
-            byte |= 0x20;
-        }
         if self.overflow {
             byte |= 0x40;
         }
@@ -59,16 +65,10 @@ impl StatusFlags {
         byte
     }
 
-    pub fn from_byte(&self, byte: u8) -> Self {
-        Self {
-            carry: byte & 0x01 != 0,
-            zero: byte & 0x02 != 0,
-            interrupt: byte & 0x04 != 0,
-            decimal: byte & 0x08 != 0,
-            brk: byte & 0x10 != 0,
-            ignored: byte & 0x20 != 0,
-            overflow: byte & 0x40 != 0,
-            negative: byte & 0x80 != 0,
+    pub fn to_pushed_byte(&self, source: PushSource) -> u8 {
+        match source {
+            PushSource::Instruction => self.to_byte() | 0x30,
+            PushSource::Interrupt => self.to_byte() | 0x20,
         }
     }
 }
@@ -0,0 +1,27 @@
+use crate::bus_access::BusAccess;
+use crate::opcodes::OpCode;
+use crate::registers::Registers;
+use crate::status_flags::StatusFlags;
+
+// What a step did. `opcode` is None when no instruction was fetched: an
+// interrupt was serviced, the CPU is halted or waiting, or a hook skipped
+// the step. Bus accesses are only recorded with trace_bus_accesses set.
+// For a branch `effective_address` is the target, taken or not.
+#[derive(Debug, Clone, Default)]
+pub struct StepInfo {
+    pub program_counter: u16,
+    pub cycles: u64,
+    pub stopped: bool,
+    pub opcode: Option<u8>,
+    pub instruction: OpCode,
+    pub operands: [u8; 2],
+    pub operand_count: usize,
+    pub effective_address: Option<u16>,
+    pub accesses: Vec<BusAccess>,
+    pub registers_before: Registers,
+    pub registers_after: Registers,
+    pub status_flags_before: StatusFlags,
+    pub status_flags_after: StatusFlags,
+    pub branch_taken: bool,
+    pub interrupt: Option<u16>,
+}
@@ -0,0 +1,43 @@
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Variant {
+    Nmos6502,
+    #[default]
+    Mos6510,
+    Mos8500,
+    Mos6507,
+    Mos6504,
+    Ricoh2A03,
+    Cmos65C02,
+}
+
+impl Variant {
+    pub fn is_cmos(self) -> bool {
+        matches!(self, Variant::Cmos65C02)
+    }
+
+    pub fn has_decimal_mode(self) -> bool {
+        !matches!(self, Variant::Ricoh2A03)
+    }
+
+    pub fn has_io_port(self) -> bool {
+        matches!(self, Variant::Mos6510 | Variant::Mos8500)
+    }
+
+    pub fn has_jmp_indirect_bug(self) -> bool {
+        !self.is_cmos()
+    }
+
+    pub fn address_bus_width(self) -> u8 {
+        match self {
+            Variant::Mos6507 | Variant::Mos6504 => 13,
+            _ => 16,
+        }
+    }
+
+    pub fn io_port_fade_cycles(self) -> u64 {
+        match self {
+            Variant::Mos8500 => 1_500_000,
+            _ => 350_000,
+        }
+    }
+}
@@ -0,0 +1,32 @@
+#[derive(Debug, Clone, Copy)]
+pub struct W65C816Registers {
+    pub program_counter: u16,
+    pub program_bank: u8,
+    pub data_bank: u8,
+    pub direct_page: u16,
+    pub stack_pointer: u16,
+    pub accumulator: u16,
+    pub x: u16,
+    pub y: u16,
+}
+
+impl Default for W65C816Registers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl W65C816Registers {
+    pub fn new() -> Self {
+        Self {
+            program_counter: 0x0000,
+            program_bank: 0x00,
+            data_bank: 0x00,
+            direct_page: 0x0000,
+            stack_pointer: 0x01ff,
+            accumulator: 0x0000,
+            x: 0x0000,
+            y: 0x0000,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::ops::{Deref, DerefMut};
+
+pub trait WideBus {
+    fn read(&mut self, address: u32) -> u8;
+    fn write(&mut self, address: u32, value: u8);
+    fn peek(&self, address: u32) -> u8;
+
+    fn write_slice(&mut self, data: &[u8], offset: u32) {
+        for (i, value) in data.iter().enumerate() {
+            self.write(offset.wrapping_add(i as u32) & 0xffffff, *value);
+        }
+    }
+}
+
+// Flat 16MB memory covering the whole 24 bit address space.
+pub struct WideMemory {
+    pub data: Vec<u8>,
+}
+
+impl Default for WideMemory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WideMemory {
+    pub fn new() -> Self {
+        Self {
+            data: vec![0; 0x1000000],
+        }
+    }
+}
+
+impl WideBus for WideMemory {
+    fn read(&mut self, address: u32) -> u8 {
+        self.data[(address & 0xffffff) as usize]
+    }
+
+    fn write(&mut self, address: u32, value: u8) {
+        self.data[(address & 0xffffff) as usize] = value;
+    }
+
+    fn peek(&self, address: u32) -> u8 {
+        self.data[(address & 0xffffff) as usize]
+    }
+
+    // Wraps at $FFFFFF like the trait default, one copy per pass.
+    fn write_slice(&mut self, mut data: &[u8], offset: u32) {
+        let mut start = (offset & 0xffffff) as usize;
+        while !data.is_empty() {
+            let len = data.len().min(self.data.len() - start);
+            self.data[start..start + len].copy_from_slice(&data[..len]);
+            data = &data[len..];
+            start = 0;
+        }
+    }
+}
+
+impl Deref for WideMemory {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl DerefMut for WideMemory {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Plain(WideMemory);
+
+    impl WideBus for Plain {
+        fn read(&mut self, address: u32) -> u8 {
+            self.0.read(address)
+        }
+
+        fn write(&mut self, address: u32, value: u8) {
+            self.0.write(address, value)
+        }
+
+        fn peek(&self, address: u32) -> u8 {
+            self.0.peek(address)
+        }
+    }
+
+    #[test]
+    fn write_slice_wraps_at_end_of_memory() {
+        let data = [0x01, 0x02, 0x03, 0x04];
+        let mut memory = WideMemory::new();
+        let mut plain = Plain(WideMemory::new());
+        memory.write_slice(&data, 0xfffffe);
+        plain.write_slice(&data, 0xfffffe);
+        assert_eq!(memory[0xfffffe..], [0x01, 0x02]);
+        assert_eq!(memory[..2], [0x03, 0x04]);
+        assert_eq!(memory.data, plain.0.data);
+
+        // Offsets past 24 bits are masked.
+        memory.write_slice(&data, 0x1000010);
+        plain.write_slice(&data, 0x1000010);
+        assert_eq!(memory[0x10..0x14], data);
+        assert_eq!(memory.data, plain.0.data);
+    }
+}